@@ -1,12 +1,21 @@
 #!/usr/bin/env cargo run --bin postgres --
 
-use clap::{Parser, Subcommand};
+use base64::Engine;
+use bytes::{BufMut, Bytes, BytesMut};
+use clap::{Parser, Subcommand, ValueEnum};
 use deadpool_postgres::{Config, Pool, Runtime};
+use futures::{pin_mut, SinkExt, StreamExt};
 use serde_json::{json, Value, Map};
+use sha2::{Digest, Sha256};
 // use std::collections::HashMap; // Unused
-use tokio_postgres::{types::ToSql, NoTls, Row};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tokio_postgres::{types::{to_sql_checked, FromSql, IsNull, Kind, ToSql, Type}, NoTls, Row, Statement};
 
-use code_tools_connectors::shared::{format_output, handle_error, parse_json_arg, // get_env_or_default, // Unused
+use code_tools_connectors::shared::{format_csv_row, format_output, handle_error, parse_json_arg, // get_env_or_default, // Unused
         OutputFormat, CommonOptions};
 
 /// PostgreSQL database CLI
@@ -65,6 +74,77 @@ enum Commands {
         /// Maximum number of rows to return
         #[arg(short, long)]
         limit: Option<i64>,
+        
+        /// Stream rows through a server-side cursor as NDJSON (or CSV with --format csv)
+        #[arg(long)]
+        stream: bool,
+        
+        /// Rows fetched from the cursor per round trip when streaming
+        #[arg(long, default_value = "1000")]
+        fetch_size: i64,
+    },
+    
+    /// Show and analyze the execution plan of a query
+    Explain {
+        /// SQL query to explain
+        query: String,
+        
+        /// Parameter values as JSON array
+        #[arg(short, long, default_value = "[]")]
+        params: String,
+        
+        /// Plan analysis options
+        #[command(flatten)]
+        explain: ExplainOptions,
+    },
+    
+    /// Profile each column of a table: nulls, distinct values, min/max, common values and width
+    Profile {
+        /// Table name
+        table: String,
+        
+        /// Only scan this percentage of the table (TABLESAMPLE SYSTEM)
+        #[arg(long)]
+        sample: Option<f64>,
+        
+        /// Take distinct counts and common values from pg_stats instead of counting
+        #[arg(long)]
+        estimate: bool,
+        
+        /// Number of most common values to report per column
+        #[arg(long, default_value = "5")]
+        top: i64,
+    },
+    
+    /// Nearest-neighbour search over a pgvector column
+    VectorSearch {
+        /// Table name
+        #[arg(long)]
+        table: String,
+        
+        /// Vector column
+        #[arg(long)]
+        column: String,
+        
+        /// Query vector (comma-separated floats or a JSON array)
+        #[arg(long)]
+        vector: String,
+        
+        /// Number of results
+        #[arg(long, default_value = "10")]
+        limit: i64,
+        
+        /// Distance metric
+        #[arg(long, value_enum, default_value = "cosine")]
+        metric: VectorMetric,
+        
+        /// Columns to return (defaults to every column except the vector)
+        #[arg(long)]
+        select: Option<String>,
+        
+        /// Filter rows (without WHERE keyword)
+        #[arg(short, long)]
+        where_clause: Option<String>,
     },
     
     /// Execute data modification operations (INSERT/UPDATE/DELETE/UPSERT)
@@ -92,6 +172,26 @@ enum Commands {
         expect_rows: bool,
     },
     
+    /// Run a multi-statement SQL file in a single transaction
+    Script {
+        /// SQL file to run
+        file: String,
+        
+        /// Wrap each statement in a savepoint so a failing statement is undone
+        /// on its own and the rest of the script still runs. The script is
+        /// still rolled back if anything failed
+        #[arg(long)]
+        savepoints: bool,
+        
+        /// Commit the statements that succeeded even when others failed
+        #[arg(long, requires = "savepoints")]
+        continue_on_error: bool,
+        
+        /// Roll back at the end instead of committing
+        #[arg(long)]
+        dry_run: bool,
+    },
+    
     /// Schema management operations
     Schema {
         #[command(subcommand)]
@@ -116,10 +216,63 @@ enum Commands {
         operation: TransferOperation,
     },
     
+    /// Schema migrations from numbered SQL files
+    Migrate {
+        /// Directory containing migration files (NNNN_name.up.sql / NNNN_name.down.sql)
+        #[arg(long, default_value = "migrations")]
+        dir: String,
+        
+        #[command(subcommand)]
+        operation: MigrateOperation,
+    },
+    
+    /// Print notifications from one or more channels as NDJSON until interrupted
+    Listen {
+        /// Channels to LISTEN on
+        #[arg(required = true)]
+        channels: Vec<String>,
+        
+        /// Exit after this many notifications
+        #[arg(short, long)]
+        count: Option<usize>,
+    },
+    
+    /// Send a notification on a channel
+    Notify {
+        /// Channel name
+        channel: String,
+        
+        /// Notification payload
+        payload: Option<String>,
+    },
+    
     /// Health check
     Health,
 }
 
+#[derive(clap::Args)]
+struct ExplainOptions {
+    /// Run the query to collect actual timings (rolled back afterwards)
+    #[arg(short, long)]
+    analyze: bool,
+    
+    /// Include buffer usage
+    #[arg(short, long)]
+    buffers: bool,
+    
+    /// Number of slowest nodes to report
+    #[arg(long, default_value = "5")]
+    top: usize,
+    
+    /// Table size at which a sequential scan is reported
+    #[arg(long, default_value = "10000")]
+    seq_scan_rows: f64,
+    
+    /// Estimated/actual row ratio at which a node is reported
+    #[arg(long, default_value = "10")]
+    misestimate_factor: f64,
+}
+
 #[derive(Subcommand)]
 enum MutateOperation {
     /// Insert new records
@@ -181,8 +334,14 @@ enum SchemaOperation {
         /// Table name
         table: String,
         
-        /// Column definitions as JSON
+        /// Column definitions as a JSON array, e.g.
+        /// [{"name": "id", "type": "bigserial", "primary_key": true},
+        ///  {"name": "email", "type": "text", "nullable": false, "unique": true}]
         columns: String,
+        
+        /// Don't fail if the table already exists
+        #[arg(long)]
+        if_not_exists: bool,
     },
     
     /// List/manage indexes
@@ -194,6 +353,38 @@ enum SchemaOperation {
         #[arg(long)]
         stats: bool,
     },
+    
+    /// Save tables, columns, indexes and constraints as a JSON snapshot
+    Snapshot {
+        /// File to write the snapshot to (prints it when omitted)
+        #[arg(short, long)]
+        output: Option<String>,
+        
+        /// Only include this schema
+        #[arg(long)]
+        schema: Option<String>,
+    },
+    
+    /// Compare this database against another database or a saved snapshot.
+    /// Changes are reported relative to the reference: `added` objects exist
+    /// only here, `removed` ones only in the reference
+    Diff {
+        /// Connection string of the reference database
+        #[arg(long, conflicts_with = "snapshot", required_unless_present = "snapshot")]
+        against_url: Option<String>,
+        
+        /// Snapshot file written by `schema snapshot`
+        #[arg(long)]
+        snapshot: Option<String>,
+        
+        /// Only compare this schema
+        #[arg(long)]
+        schema: Option<String>,
+        
+        /// Include the DDL that would make this database match the reference
+        #[arg(long)]
+        ddl: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -209,10 +400,45 @@ enum UserOperation {
         /// Password
         password: String,
         
-        /// Additional options as JSON
+        /// Role attributes and permissions as JSON, e.g.
+        /// {"createdb": true, "in_roles": ["readers"], "permissions": {"tables": {"public.*": ["SELECT"]}}}
         #[arg(short, long, default_value = "{}")]
         options: String,
     },
+    
+    /// Grant privileges to a user
+    Grant {
+        /// Username
+        username: String,
+        
+        /// Permissions as JSON (database, schemas, tables, sequences, roles)
+        #[arg(short, long)]
+        permissions: String,
+    },
+    
+    /// Revoke privileges from a user
+    Revoke {
+        /// Username
+        username: String,
+        
+        /// Permissions as JSON (same shape as grant)
+        #[arg(short, long)]
+        permissions: String,
+    },
+    
+    /// Drop a user
+    Drop {
+        /// Username
+        username: String,
+        
+        /// Reassign owned objects to this role and drop remaining privileges first
+        #[arg(long)]
+        reassign_to: Option<String>,
+        
+        /// Don't fail if the user does not exist
+        #[arg(long)]
+        if_exists: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -223,7 +449,7 @@ enum MonitorOperation {
     /// Monitor active connections
     Connections,
     
-    /// Show slow queries
+    /// Show slow queries (from pg_stat_statements when installed and preloaded, otherwise running queries)
     SlowQueries {
         /// Minimum duration in milliseconds
         #[arg(short, long, default_value = "1000")]
@@ -235,9 +461,35 @@ enum MonitorOperation {
     },
 }
 
+#[derive(Subcommand)]
+enum MigrateOperation {
+    /// Apply pending migrations
+    Up {
+        /// Stop after applying this version
+        #[arg(long)]
+        target: Option<i64>,
+    },
+    
+    /// Roll back applied migrations using their down files
+    Down {
+        /// Number of migrations to roll back
+        #[arg(long, default_value = "1")]
+        steps: usize,
+    },
+    
+    /// Show applied, pending and drifted migrations
+    Status,
+    
+    /// Create a new pair of empty migration files
+    New {
+        /// Migration name
+        name: String,
+    },
+}
+
 #[derive(Subcommand)]
 enum TransferOperation {
-    /// Export table data to JSON
+    /// Export table data to JSON Lines, CSV or JSON
     Export {
         /// Table name
         table: String,
@@ -253,17 +505,102 @@ enum TransferOperation {
         /// Limit rows
         #[arg(short, long)]
         limit: Option<i64>,
+        
+        /// File format (inferred from the output extension if omitted)
+        #[arg(long, value_enum)]
+        file_format: Option<TransferFormat>,
+    },
+    
+    /// Import JSON Lines, CSV or JSON data into an existing table
+    Import {
+        /// Table name
+        table: String,
+        
+        /// Input file path
+        #[arg(short, long)]
+        input: String,
+        
+        /// File format (inferred from the input extension if omitted)
+        #[arg(long, value_enum)]
+        file_format: Option<TransferFormat>,
     },
 }
 
-async fn create_pool(
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum TransferFormat {
+    /// One JSON object per line
+    Jsonl,
+    /// CSV with a header row
+    Csv,
+    /// A single JSON array of objects
+    Json,
+}
+
+impl TransferFormat {
+    fn resolve(explicit: Option<TransferFormat>, path: &str) -> TransferFormat {
+        if let Some(format) = explicit {
+            return format;
+        }
+        
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => TransferFormat::Csv,
+            Some(ext) if ext.eq_ignore_ascii_case("json") => TransferFormat::Json,
+            _ => TransferFormat::Jsonl,
+        }
+    }
+    
+    fn as_str(&self) -> &'static str {
+        match self {
+            TransferFormat::Jsonl => "jsonl",
+            TransferFormat::Csv => "csv",
+            TransferFormat::Json => "json",
+        }
+    }
+}
+
+/// Distance metrics for pgvector searches, named as in the qdrant tool
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum VectorMetric {
+    /// Cosine distance (<=>); score is the cosine similarity
+    Cosine,
+    /// Inner product (<#>); score is the dot product
+    Dot,
+    /// Euclidean distance (<->)
+    Euclid,
+    /// Taxicab distance (<+>), pgvector 0.7+
+    Manhattan,
+}
+
+impl VectorMetric {
+    fn operator(&self) -> &'static str {
+        match self {
+            VectorMetric::Cosine => "<=>",
+            VectorMetric::Dot => "<#>",
+            VectorMetric::Euclid => "<->",
+            VectorMetric::Manhattan => "<+>",
+        }
+    }
+    
+    /// Turn an operator distance into a qdrant-style score
+    fn score(&self, distance: f64) -> f64 {
+        match self {
+            VectorMetric::Cosine => 1.0 - distance,
+            // <#> returns the negative inner product so that ascending order is best first
+            VectorMetric::Dot => -distance,
+            VectorMetric::Euclid | VectorMetric::Manhattan => distance,
+        }
+    }
+}
+
+/// Connection settings shared by the pool and dedicated connections
+fn connection_config(
     database_url: Option<String>,
     host: String,
     port: u16,
     database: String,
     user: String,
     password: String,
-) -> Result<Pool, anyhow::Error> {
+) -> Config {
     let mut cfg = Config::new();
     
     if let Some(_url) = database_url {
@@ -279,6 +616,10 @@ async fn create_pool(
         cfg.password = Some(password);
     }
     
+    cfg
+}
+
+async fn create_pool(mut cfg: Config) -> Result<Pool, anyhow::Error> {
     cfg.manager = Some(deadpool_postgres::ManagerConfig {
         recycling_method: deadpool_postgres::RecyclingMethod::Fast,
     });
@@ -298,278 +639,4265 @@ fn row_to_json(row: &Row) -> Result<Value, anyhow::Error> {
     Ok(Value::Object(obj))
 }
 
-fn postgres_value_to_json(row: &Row, idx: usize, col_type: &tokio_postgres::types::Type) -> Result<Value, anyhow::Error> {
-    use tokio_postgres::types::Type;
-    
-    if row.try_get::<_, Option<String>>(idx)?.is_none() {
-        return Ok(Value::Null);
+/// Borrows a column's raw binary value without decoding it
+struct RawValue<'a>(&'a [u8]);
+
+impl<'a> FromSql<'a> for RawValue<'a> {
+    fn from_sql(_ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        Ok(RawValue(raw))
     }
     
-    match *col_type {
-        Type::BOOL => Ok(Value::Bool(row.get(idx))),
-        Type::INT2 => Ok(Value::Number(serde_json::Number::from(row.get::<_, i16>(idx)))),
-        Type::INT4 => Ok(Value::Number(serde_json::Number::from(row.get::<_, i32>(idx)))),
-        Type::INT8 => Ok(Value::Number(serde_json::Number::from(row.get::<_, i64>(idx)))),
-        Type::FLOAT4 => {
-            let val: f32 = row.get(idx);
-            Ok(serde_json::Number::from_f64(val as f64)
-                .map(Value::Number)
-                .unwrap_or(Value::Null))
-        },
-        Type::FLOAT8 => {
-            let val: f64 = row.get(idx);
-            Ok(serde_json::Number::from_f64(val)
-                .map(Value::Number)
-                .unwrap_or(Value::Null))
-        },
-        Type::TEXT | Type::VARCHAR | Type::CHAR | Type::NAME => {
-            Ok(Value::String(row.get(idx)))
-        },
-        Type::JSON | Type::JSONB => {
-            let json_val: serde_json::Value = row.get(idx);
-            Ok(json_val)
-        },
-        Type::UUID => {
-            let uuid: uuid::Uuid = row.get(idx);
-            Ok(Value::String(uuid.to_string()))
-        },
-        Type::TIMESTAMP => {
-            let ts: chrono::NaiveDateTime = row.get(idx);
-            Ok(Value::String(ts.to_string()))
-        },
-        Type::TIMESTAMPTZ => {
-            let ts: chrono::DateTime<chrono::Utc> = row.get(idx);
-            Ok(Value::String(ts.to_rfc3339()))
-        },
-        Type::DATE => {
-            let date: chrono::NaiveDate = row.get(idx);
-            Ok(Value::String(date.to_string()))
-        },
-        Type::TIME => {
-            let time: chrono::NaiveTime = row.get(idx);
-            Ok(Value::String(time.to_string()))
-        },
-        _ => {
-            // Fallback to string representation
-            let val: String = row.get(idx);
-            Ok(Value::String(val))
-        },
+    fn accepts(_ty: &Type) -> bool {
+        true
     }
 }
 
-fn json_to_sql_params(params: &Value) -> Result<Vec<Box<dyn ToSql + Send + Sync>>, anyhow::Error> {
-    match params {
-        Value::Array(arr) => {
-            let mut sql_params: Vec<Box<dyn ToSql + Send + Sync>> = Vec::new();
-            
-            for value in arr {
-                match value {
-                    Value::String(s) => sql_params.push(Box::new(s.clone())),
-                    Value::Number(n) => {
-                        if let Some(i) = n.as_i64() {
-                            sql_params.push(Box::new(i));
-                        } else if let Some(f) = n.as_f64() {
-                            sql_params.push(Box::new(f));
-                        } else {
-                            return Err(anyhow::anyhow!("Invalid number parameter"));
-                        }
-                    },
-                    Value::Bool(b) => sql_params.push(Box::new(*b)),
-                    Value::Null => sql_params.push(Box::new(Option::<String>::None)),
-                    _ => return Err(anyhow::anyhow!("Unsupported parameter type")),
-                }
-            }
-            
-            Ok(sql_params)
-        },
-        _ => Err(anyhow::anyhow!("Parameters must be a JSON array")),
-    }
+/// Cursor over a binary-format value
+struct ByteReader<'a> {
+    buf: &'a [u8],
 }
 
-async fn handle_select_command(
-    pool: &Pool,
-    query: String,
-    params_str: String,
-    limit: Option<i64>,
-    options: &CommonOptions,
-) -> Result<(), anyhow::Error> {
-    let params_json: Value = parse_json_arg(&params_str, "parameters")?;
-    let _sql_params = json_to_sql_params(&params_json)?;
+impl<'a> ByteReader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
     
-    let mut final_query = query;
-    if let Some(limit_val) = limit {
-        if !final_query.to_uppercase().contains("LIMIT") {
-            final_query.push_str(&format!(" LIMIT {}", limit_val));
+    fn take(&mut self, len: usize) -> Result<&'a [u8], anyhow::Error> {
+        if self.buf.len() < len {
+            return Err(anyhow::anyhow!("Unexpected end of binary value"));
         }
+        let (head, tail) = self.buf.split_at(len);
+        self.buf = tail;
+        Ok(head)
     }
     
-    let client = pool.get().await?;
-    let rows = client.query(&final_query, &[]).await?;
+    fn u8(&mut self) -> Result<u8, anyhow::Error> {
+        Ok(self.take(1)?[0])
+    }
     
-    let mut results = Vec::new();
-    for row in rows {
-        results.push(row_to_json(&row)?);
+    fn u16(&mut self) -> Result<u16, anyhow::Error> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into()?))
     }
     
-    let result_json = Value::Array(results);
-    println!("{}", format_output(&result_json, options.format));
+    fn i16(&mut self) -> Result<i16, anyhow::Error> {
+        Ok(i16::from_be_bytes(self.take(2)?.try_into()?))
+    }
     
-    Ok(())
-}
-
-async fn handle_mutate_insert(
-    pool: &Pool,
-    table: String,
-    data_str: String,
-    returning: Option<String>,
-    options: &CommonOptions,
-) -> Result<(), anyhow::Error> {
-    let data_json: Value = parse_json_arg(&data_str, "data")?;
+    fn u32(&mut self) -> Result<u32, anyhow::Error> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into()?))
+    }
     
-    let records = match data_json {
-        Value::Array(arr) => arr,
-        Value::Object(_) => vec![data_json],
-        _ => return Err(anyhow::anyhow!("Data must be JSON object or array of objects")),
-    };
+    fn i32(&mut self) -> Result<i32, anyhow::Error> {
+        Ok(i32::from_be_bytes(self.take(4)?.try_into()?))
+    }
     
-    if records.is_empty() {
-        return Err(anyhow::anyhow!("No data to insert"));
+    fn u64(&mut self) -> Result<u64, anyhow::Error> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into()?))
     }
     
-    // Get column names from first record
-    let first_record = records.first().unwrap();
-    if let Value::Object(obj) = first_record {
-        let columns: Vec<String> = obj.keys().cloned().collect();
-        let placeholders: Vec<String> = (1..=columns.len()).map(|i| format!("${}", i)).collect();
-        
-        let mut insert_query = format!(
-            "INSERT INTO {} ({}) VALUES ({})",
-            table,
-            columns.join(", "),
-            placeholders.join(", ")
-        );
-        
-        if let Some(ref ret) = returning {
-            insert_query.push_str(&format!(" RETURNING {}", ret));
-        }
-        
-        let client = pool.get().await?;
-        let mut results = Vec::new();
-        
-        for record in records {
-            if let Value::Object(record_obj) = record {
-                let mut values = Vec::new();
-                for col in &columns {
-                    let value = record_obj.get(col).cloned().unwrap_or(Value::Null);
-                    values.push(value);
-                }
-                
-                if returning.is_some() {
-                    let rows = client.query(&insert_query, &[]).await?;
-                    for row in rows {
-                        results.push(row_to_json(&row)?);
-                    }
-                } else {
-                    client.execute(&insert_query, &[]).await?;
-                    results.push(json!({"inserted": true}));
-                }
-            }
-        }
-        
-        let result_json = Value::Array(results);
-        println!("{}", format_output(&result_json, options.format));
+    fn i64(&mut self) -> Result<i64, anyhow::Error> {
+        Ok(i64::from_be_bytes(self.take(8)?.try_into()?))
     }
     
-    Ok(())
-}
-
-async fn handle_execute_command(
-    pool: &Pool,
-    sql: String,
-    params_str: String,
-    transactional: bool,
-    expect_rows: bool,
-    options: &CommonOptions,
-) -> Result<(), anyhow::Error> {
-    let params_json: Value = parse_json_arg(&params_str, "parameters")?;
-    let _sql_params = json_to_sql_params(&params_json)?;
+    fn f32(&mut self) -> Result<f32, anyhow::Error> {
+        Ok(f32::from_be_bytes(self.take(4)?.try_into()?))
+    }
     
-    let mut client = pool.get().await?;
+    fn f64(&mut self) -> Result<f64, anyhow::Error> {
+        Ok(f64::from_be_bytes(self.take(8)?.try_into()?))
+    }
     
-    if transactional {
-        let transaction = client.transaction().await?;
-        
-        if expect_rows {
-            let rows = transaction.query(&sql, &[]).await?;
-            let mut results = Vec::new();
-            for row in rows {
-                results.push(row_to_json(&row)?);
-            }
-            transaction.commit().await?;
-            
-            let result_json = Value::Array(results);
-            println!("{}", format_output(&result_json, options.format));
-        } else {
-            let affected = transaction.execute(&sql, &[]).await?;
-            transaction.commit().await?;
-            
-            let result = json!({"affected_rows": affected});
-            println!("{}", format_output(&result, options.format));
-        }
-    } else {
-        if expect_rows {
-            let rows = client.query(&sql, &[]).await?;
-            let mut results = Vec::new();
-            for row in rows {
-                results.push(row_to_json(&row)?);
-            }
-            
-            let result_json = Value::Array(results);
-            println!("{}", format_output(&result_json, options.format));
+    /// Read a non-negative 32-bit length or count
+    fn count(&mut self) -> Result<usize, anyhow::Error> {
+        let count = self.i32()?;
+        usize::try_from(count).map_err(|_| anyhow::anyhow!("Negative length {} in binary value", count))
+    }
+    
+    /// Read a length-prefixed value, where a length of -1 means NULL
+    fn value(&mut self) -> Result<Option<&'a [u8]>, anyhow::Error> {
+        let len = self.i32()?;
+        if len < 0 {
+            Ok(None)
         } else {
-            let affected = client.execute(&sql, &[]).await?;
-            let result = json!({"affected_rows": affected});
-            println!("{}", format_output(&result, options.format));
+            Ok(Some(self.take(len as usize)?))
         }
     }
     
-    Ok(())
+    fn cstr(&mut self) -> Result<&'a str, anyhow::Error> {
+        let end = self.buf.iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| anyhow::anyhow!("Unterminated string in binary value"))?;
+        let s = std::str::from_utf8(&self.buf[..end])?;
+        self.buf = &self.buf[end + 1..];
+        Ok(s)
+    }
+}
+
+fn postgres_value_to_json(row: &Row, idx: usize, col_type: &Type) -> Result<Value, anyhow::Error> {
+    match row.try_get::<_, Option<RawValue>>(idx)? {
+        Some(RawValue(raw)) => decode_pg_value(col_type, raw)
+            .map_err(|e| anyhow::anyhow!("Failed to decode column '{}' of type {}: {}",
+                row.columns()[idx].name(), col_type.name(), e)),
+        None => Ok(Value::Null),
+    }
+}
+
+/// Days between the Unix epoch and the PostgreSQL epoch (2000-01-01)
+const PG_EPOCH_OFFSET_DAYS: i64 = 10_957;
+
+fn pg_epoch() -> chrono::NaiveDateTime {
+    chrono::NaiveDate::from_ymd_opt(2000, 1, 1)
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .unwrap()
+}
+
+/// Decode a binary-format value into its JSON representation.
+///
+/// Integers, floats and booleans map to JSON scalars, NUMERIC and MONEY to
+/// lossless strings, bytea to base64, date/time types to ISO strings, arrays to
+/// (nested) JSON arrays, composites to objects and ranges to bound objects.
+/// Types without a known binary layout fall back to `{"type", "base64"}`.
+fn decode_pg_value(ty: &Type, raw: &[u8]) -> Result<Value, anyhow::Error> {
+    match ty.kind() {
+        Kind::Array(element) => return decode_pg_array(element, raw),
+        Kind::Range(element) => return decode_pg_range(element, raw),
+        Kind::Multirange(element) => {
+            let mut reader = ByteReader::new(raw);
+            let count = reader.i32()?;
+            let mut ranges = Vec::new();
+            for _ in 0..count {
+                let bytes = reader.value()?
+                    .ok_or_else(|| anyhow::anyhow!("NULL range inside multirange"))?;
+                ranges.push(decode_pg_range(element, bytes)?);
+            }
+            return Ok(Value::Array(ranges));
+        },
+        Kind::Domain(base) => return decode_pg_value(base, raw),
+        Kind::Enum(_) => return Ok(Value::String(std::str::from_utf8(raw)?.to_string())),
+        Kind::Composite(fields) => {
+            let names: Vec<(String, Type)> = fields.iter()
+                .map(|f| (f.name().to_string(), f.type_().clone()))
+                .collect();
+            return decode_pg_record(raw, Some(&names));
+        },
+        _ => {},
+    }
+    
+    let mut reader = ByteReader::new(raw);
+    
+    let value = match *ty {
+        Type::BOOL => Value::Bool(reader.u8()? != 0),
+        Type::INT2 => json!(reader.i16()?),
+        Type::INT4 => json!(reader.i32()?),
+        Type::INT8 => json!(reader.i64()?),
+        Type::OID | Type::XID | Type::CID | Type::REGPROC | Type::REGPROCEDURE | Type::REGOPER
+        | Type::REGOPERATOR | Type::REGCLASS | Type::REGTYPE | Type::REGCONFIG | Type::REGDICTIONARY
+        | Type::REGNAMESPACE | Type::REGROLE | Type::REGCOLLATION => json!(reader.u32()?),
+        Type::XID8 => json!(reader.u64()?),
+        Type::FLOAT4 => f32_to_json(reader.f32()?),
+        Type::FLOAT8 => float_to_json(reader.f64()?),
+        Type::NUMERIC => Value::String(decode_pg_numeric(raw)?),
+        Type::MONEY => {
+            // Assumes the default of two fractional digits from lc_monetary
+            let cents = reader.i64()? as i128;
+            let sign = if cents < 0 { "-" } else { "" };
+            Value::String(format!("{}{}.{:02}", sign, cents.abs() / 100, cents.abs() % 100))
+        },
+        Type::CHAR => Value::String(((reader.u8()?) as char).to_string()),
+        Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME | Type::UNKNOWN | Type::XML
+        | Type::REFCURSOR | Type::CSTRING | Type::PG_NODE_TREE => {
+            Value::String(std::str::from_utf8(raw)?.to_string())
+        },
+        Type::BYTEA => Value::String(base64::engine::general_purpose::STANDARD.encode(raw)),
+        Type::JSON => serde_json::from_slice(raw)?,
+        Type::JSONB => {
+            let version = reader.u8()?;
+            if version != 1 {
+                return Err(anyhow::anyhow!("Unsupported jsonb version {}", version));
+            }
+            serde_json::from_slice(reader.buf)?
+        },
+        Type::JSONPATH => {
+            reader.u8()?;
+            Value::String(std::str::from_utf8(reader.buf)?.to_string())
+        },
+        Type::UUID => Value::String(uuid::Uuid::from_slice(raw)?.to_string()),
+        Type::DATE => {
+            let days = reader.i32()?;
+            match days {
+                i32::MAX => json!("infinity"),
+                i32::MIN => json!("-infinity"),
+                _ => {
+                    let date = chrono::NaiveDate::from_num_days_from_ce_opt(
+                        (days as i64 + PG_EPOCH_OFFSET_DAYS + 719_163) as i32
+                    ).ok_or_else(|| anyhow::anyhow!("Date out of range"))?;
+                    Value::String(date.to_string())
+                },
+            }
+        },
+        Type::TIMESTAMP | Type::TIMESTAMPTZ => {
+            let micros = reader.i64()?;
+            match micros {
+                i64::MAX => json!("infinity"),
+                i64::MIN => json!("-infinity"),
+                _ => {
+                    let ts = pg_epoch()
+                        .checked_add_signed(chrono::Duration::microseconds(micros))
+                        .ok_or_else(|| anyhow::anyhow!("Timestamp out of range"))?;
+                    if *ty == Type::TIMESTAMPTZ {
+                        Value::String(ts.and_utc().to_rfc3339())
+                    } else {
+                        Value::String(ts.to_string())
+                    }
+                },
+            }
+        },
+        Type::TIME => Value::String(format_pg_time(reader.i64()?)?),
+        Type::TIMETZ => {
+            let time = format_pg_time(reader.i64()?)?;
+            // The zone is stored as seconds west of UTC
+            let offset = -reader.i32()?;
+            let sign = if offset < 0 { '-' } else { '+' };
+            let offset = offset.abs();
+            Value::String(format!("{}{}{:02}:{:02}", time, sign, offset / 3600, (offset % 3600) / 60))
+        },
+        Type::INTERVAL => {
+            let micros = reader.i64()?;
+            let days = reader.i32()?;
+            let months = reader.i32()?;
+            Value::String(format_pg_interval(months, days, micros))
+        },
+        Type::INET | Type::CIDR => {
+            let family = reader.u8()?;
+            let bits = reader.u8()?;
+            let _is_cidr = reader.u8()?;
+            let len = reader.u8()? as usize;
+            let addr = reader.take(len)?;
+            let (ip, max_bits) = match family {
+                2 => (std::net::IpAddr::from(<[u8; 4]>::try_from(addr)?), 32),
+                _ => (std::net::IpAddr::from(<[u8; 16]>::try_from(addr)?), 128),
+            };
+            if *ty == Type::INET && bits == max_bits {
+                Value::String(ip.to_string())
+            } else {
+                Value::String(format!("{}/{}", ip, bits))
+            }
+        },
+        Type::MACADDR | Type::MACADDR8 => Value::String(
+            raw.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(":")
+        ),
+        Type::BIT | Type::VARBIT => {
+            let len = reader.count()?;
+            let bytes = reader.take(len.div_ceil(8))?;
+            Value::String((0..len)
+                .map(|i| if bytes[i / 8] & (0x80 >> (i % 8)) != 0 { '1' } else { '0' })
+                .collect())
+        },
+        Type::TID => {
+            let block = reader.u32()?;
+            let offset = reader.u16()?;
+            Value::String(format!("({},{})", block, offset))
+        },
+        Type::PG_LSN => {
+            let lsn = reader.u64()?;
+            Value::String(format!("{:X}/{:X}", lsn >> 32, lsn & 0xFFFF_FFFF))
+        },
+        Type::TXID_SNAPSHOT | Type::PG_SNAPSHOT => {
+            let count = reader.i32()?;
+            let xmin = reader.u64()?;
+            let xmax = reader.u64()?;
+            let xips = (0..count)
+                .map(|_| reader.u64().map(|x| x.to_string()))
+                .collect::<Result<Vec<_>, _>>()?;
+            Value::String(format!("{}:{}:{}", xmin, xmax, xips.join(",")))
+        },
+        Type::POINT => point_to_json(&mut reader)?,
+        Type::LSEG | Type::BOX => json!([point_to_json(&mut reader)?, point_to_json(&mut reader)?]),
+        Type::LINE => json!({"a": reader.f64()?, "b": reader.f64()?, "c": reader.f64()?}),
+        Type::CIRCLE => json!({"x": reader.f64()?, "y": reader.f64()?, "radius": reader.f64()?}),
+        Type::PATH => {
+            let closed = reader.u8()? != 0;
+            let count = reader.i32()?;
+            let points = (0..count)
+                .map(|_| point_to_json(&mut reader))
+                .collect::<Result<Vec<_>, _>>()?;
+            json!({"closed": closed, "points": points})
+        },
+        Type::POLYGON => {
+            let count = reader.i32()?;
+            Value::Array((0..count)
+                .map(|_| point_to_json(&mut reader))
+                .collect::<Result<Vec<_>, _>>()?)
+        },
+        Type::TS_VECTOR => Value::String(decode_pg_tsvector(raw)?),
+        Type::TSQUERY => Value::String(decode_pg_tsquery(raw)?),
+        Type::INT2_VECTOR | Type::OID_VECTOR => {
+            let element = if *ty == Type::INT2_VECTOR { Type::INT2 } else { Type::OID };
+            decode_pg_array(&element, raw)?
+        },
+        Type::RECORD => decode_pg_record(raw, None)?,
+        Type::VOID => Value::Null,
+        _ => match ty.name() {
+            "citext" => Value::String(std::str::from_utf8(raw)?.to_string()),
+            "ltree" | "lquery" | "ltxtquery" => {
+                reader.u8()?;
+                Value::String(std::str::from_utf8(reader.buf)?.to_string())
+            },
+            "hstore" => {
+                let count = reader.i32()?;
+                let mut map = Map::new();
+                for _ in 0..count {
+                    let key = reader.value()?
+                        .ok_or_else(|| anyhow::anyhow!("NULL hstore key"))?;
+                    let value = reader.value()?
+                        .map(|v| std::str::from_utf8(v).map(|s| Value::String(s.to_string())))
+                        .transpose()?
+                        .unwrap_or(Value::Null);
+                    map.insert(std::str::from_utf8(key)?.to_string(), value);
+                }
+                Value::Object(map)
+            },
+            // pgvector: dimensions and an unused word, then float4 or float2 elements
+            "vector" | "halfvec" => {
+                let dimensions = reader.u16()?;
+                reader.u16()?;
+                let mut elements = Vec::with_capacity(dimensions as usize);
+                for _ in 0..dimensions {
+                    let element = if ty.name() == "vector" { reader.f32()? } else { f16_to_f32(reader.u16()?) };
+                    elements.push(f32_to_json(element));
+                }
+                Value::Array(elements)
+            },
+            "sparsevec" => {
+                let dimensions = reader.i32()?;
+                let count = reader.i32()?;
+                reader.i32()?;
+                let indices = (0..count).map(|_| reader.i32()).collect::<Result<Vec<_>, _>>()?;
+                let values = (0..count).map(|_| reader.f32().map(f32_to_json)).collect::<Result<Vec<_>, _>>()?;
+                json!({ "dimensions": dimensions, "indices": indices, "values": values })
+            },
+            _ => json!({
+                "type": ty.name(),
+                "base64": base64::engine::general_purpose::STANDARD.encode(raw)
+            }),
+        },
+    };
+    
+    Ok(value)
+}
+
+fn float_to_json(value: f64) -> Value {
+    if value.is_nan() {
+        json!("NaN")
+    } else if value.is_infinite() {
+        json!(if value > 0.0 { "Infinity" } else { "-Infinity" })
+    } else {
+        serde_json::Number::from_f64(value)
+            .map(Value::Number)
+            .unwrap_or(Value::Null)
+    }
+}
+
+/// Widen a float4 through its shortest decimal form, so 0.1 stays 0.1
+fn f32_to_json(value: f32) -> Value {
+    if value.is_finite() {
+        value.to_string().parse::<f64>().map(float_to_json).unwrap_or(Value::Null)
+    } else {
+        float_to_json(value as f64)
+    }
+}
+
+/// Convert IEEE 754 half-precision bits, as used by pgvector's halfvec
+fn f16_to_f32(bits: u16) -> f32 {
+    let negative = bits & 0x8000 != 0;
+    let exponent = ((bits >> 10) & 0x1f) as u32;
+    let mantissa = (bits & 0x3ff) as u32;
+    let magnitude = match exponent {
+        0 => mantissa as f32 * 2f32.powi(-24),
+        0x1f if mantissa == 0 => f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => f32::from_bits(((exponent + 112) << 23) | (mantissa << 13)),
+    };
+    if negative { -magnitude } else { magnitude }
+}
+
+fn point_to_json(reader: &mut ByteReader) -> Result<Value, anyhow::Error> {
+    Ok(json!({"x": reader.f64()?, "y": reader.f64()?}))
+}
+
+fn format_pg_time(micros: i64) -> Result<String, anyhow::Error> {
+    // 24:00:00 is a valid PostgreSQL time but not a valid chrono time
+    if micros == 86_400_000_000 {
+        return Ok("24:00:00".to_string());
+    }
+    let time = chrono::NaiveTime::from_num_seconds_from_midnight_opt(
+        (micros / 1_000_000) as u32,
+        ((micros % 1_000_000) * 1000) as u32,
+    ).ok_or_else(|| anyhow::anyhow!("Time out of range"))?;
+    Ok(time.to_string())
+}
+
+/// Format an interval as an ISO 8601 duration, matching PostgreSQL's iso_8601 interval style
+fn format_pg_interval(months: i32, days: i32, micros: i64) -> String {
+    if months == 0 && days == 0 && micros == 0 {
+        return "PT0S".to_string();
+    }
+    
+    let mut out = String::from("P");
+    let years = months / 12;
+    let months = months % 12;
+    if years != 0 {
+        out.push_str(&format!("{}Y", years));
+    }
+    if months != 0 {
+        out.push_str(&format!("{}M", months));
+    }
+    if days != 0 {
+        out.push_str(&format!("{}D", days));
+    }
+    
+    if micros != 0 {
+        out.push('T');
+        let hours = micros / 3_600_000_000;
+        let minutes = (micros % 3_600_000_000) / 60_000_000;
+        let second_micros = micros % 60_000_000;
+        if hours != 0 {
+            out.push_str(&format!("{}H", hours));
+        }
+        if minutes != 0 {
+            out.push_str(&format!("{}M", minutes));
+        }
+        if second_micros != 0 {
+            let sign = if second_micros < 0 { "-" } else { "" };
+            let abs = second_micros.abs();
+            let mut seconds = format!("{}{}", sign, abs / 1_000_000);
+            if abs % 1_000_000 != 0 {
+                let fraction = format!("{:06}", abs % 1_000_000);
+                seconds.push('.');
+                seconds.push_str(fraction.trim_end_matches('0'));
+            }
+            out.push_str(&format!("{}S", seconds));
+        }
+    }
+    
+    out
+}
+
+/// Decode a binary NUMERIC into its exact decimal string
+fn decode_pg_numeric(raw: &[u8]) -> Result<String, anyhow::Error> {
+    let mut reader = ByteReader::new(raw);
+    let ndigits = reader.i16()? as i32;
+    let weight = reader.i16()? as i32;
+    let sign = reader.u16()?;
+    let dscale = reader.u16()? as usize;
+    let digits = (0..ndigits)
+        .map(|_| reader.i16())
+        .collect::<Result<Vec<_>, _>>()?;
+    
+    match sign {
+        0xC000 => return Ok("NaN".to_string()),
+        0xD000 => return Ok("Infinity".to_string()),
+        0xF000 => return Ok("-Infinity".to_string()),
+        _ => {},
+    }
+    
+    // Digits are base 10000, with digits[k] scaled by 10000^(weight - k)
+    let digit_at = |k: i32| -> i16 {
+        if k >= 0 && k < ndigits { digits[k as usize] } else { 0 }
+    };
+    
+    let mut out = String::new();
+    if sign == 0x4000 {
+        out.push('-');
+    }
+    
+    if weight < 0 {
+        out.push('0');
+    } else {
+        out.push_str(&digit_at(0).to_string());
+        for k in 1..=weight {
+            out.push_str(&format!("{:04}", digit_at(k)));
+        }
+    }
+    
+    if dscale > 0 {
+        let mut fraction = String::new();
+        let mut k = weight + 1;
+        while fraction.len() < dscale {
+            fraction.push_str(&format!("{:04}", digit_at(k)));
+            k += 1;
+        }
+        fraction.truncate(dscale);
+        out.push('.');
+        out.push_str(&fraction);
+    }
+    
+    Ok(out)
+}
+
+fn decode_pg_array(element: &Type, raw: &[u8]) -> Result<Value, anyhow::Error> {
+    let mut reader = ByteReader::new(raw);
+    let ndim = reader.count()?;
+    let _has_nulls = reader.i32()?;
+    let _element_oid = reader.u32()?;
+    
+    let mut dims = Vec::new();
+    for _ in 0..ndim {
+        dims.push(reader.count()?);
+        let _lower_bound = reader.i32()?;
+    }
+    
+    // PostgreSQL sends empty arrays with no dimensions, but treat a zero-length one the same way
+    let total = dims.iter()
+        .try_fold(1usize, |acc, &dim| acc.checked_mul(dim))
+        .ok_or_else(|| anyhow::anyhow!("Array dimensions {:?} are too large", dims))?;
+    if ndim == 0 || total == 0 {
+        return Ok(Value::Array(Vec::new()));
+    }
+    
+    // Every element carries at least a 4-byte length, so don't trust the header for the capacity
+    let mut values = Vec::with_capacity(total.min(reader.buf.len() / 4));
+    for _ in 0..total {
+        values.push(match reader.value()? {
+            Some(bytes) => decode_pg_value(element, bytes)?,
+            None => Value::Null,
+        });
+    }
+    
+    // Fold the flat element list into nested arrays, innermost dimension first
+    for &dim in dims[1..].iter().rev() {
+        values = values.chunks(dim)
+            .map(|chunk| Value::Array(chunk.to_vec()))
+            .collect();
+    }
+    
+    Ok(Value::Array(values))
+}
+
+fn decode_pg_range(element: &Type, raw: &[u8]) -> Result<Value, anyhow::Error> {
+    const EMPTY: u8 = 0x01;
+    const LOWER_INCLUSIVE: u8 = 0x02;
+    const UPPER_INCLUSIVE: u8 = 0x04;
+    const LOWER_INFINITE: u8 = 0x08;
+    const UPPER_INFINITE: u8 = 0x10;
+    
+    let mut reader = ByteReader::new(raw);
+    let flags = reader.u8()?;
+    
+    if flags & EMPTY != 0 {
+        return Ok(json!({"empty": true}));
+    }
+    
+    let mut bound = |infinite: bool| -> Result<Value, anyhow::Error> {
+        if infinite {
+            return Ok(Value::Null);
+        }
+        match reader.value()? {
+            Some(bytes) => decode_pg_value(element, bytes),
+            None => Ok(Value::Null),
+        }
+    };
+    
+    let lower = bound(flags & LOWER_INFINITE != 0)?;
+    let upper = bound(flags & UPPER_INFINITE != 0)?;
+    
+    Ok(json!({
+        "lower": lower,
+        "upper": upper,
+        "lower_inclusive": flags & LOWER_INCLUSIVE != 0,
+        "upper_inclusive": flags & UPPER_INCLUSIVE != 0
+    }))
+}
+
+/// Decode a composite or anonymous record. Anonymous record fields are keyed
+/// f1, f2, ... like PostgreSQL's row_to_json.
+fn decode_pg_record(raw: &[u8], fields: Option<&[(String, Type)]>) -> Result<Value, anyhow::Error> {
+    let mut reader = ByteReader::new(raw);
+    let count = reader.i32()? as usize;
+    let mut obj = Map::new();
+    
+    for i in 0..count {
+        let oid = reader.u32()?;
+        let bytes = reader.value()?;
+        
+        let (name, field_type) = match fields.and_then(|f| f.get(i)) {
+            Some((name, field_type)) => (name.clone(), Some(field_type.clone())),
+            None => (format!("f{}", i + 1), Type::from_oid(oid)),
+        };
+        
+        let value = match (bytes, field_type) {
+            (None, _) => Value::Null,
+            (Some(bytes), Some(field_type)) => decode_pg_value(&field_type, bytes)?,
+            (Some(bytes), None) => json!({
+                "oid": oid,
+                "base64": base64::engine::general_purpose::STANDARD.encode(bytes)
+            }),
+        };
+        obj.insert(name, value);
+    }
+    
+    Ok(Value::Object(obj))
+}
+
+/// Quote a text-search lexeme the way PostgreSQL prints it
+fn quote_lexeme(lexeme: &str) -> String {
+    format!("'{}'", lexeme.replace('\\', "\\\\").replace('\'', "''"))
+}
+
+/// Decode a binary tsvector into PostgreSQL's text form, e.g. `'cat':3 'fat':2A`
+fn decode_pg_tsvector(raw: &[u8]) -> Result<String, anyhow::Error> {
+    let mut reader = ByteReader::new(raw);
+    let count = reader.i32()?;
+    let mut lexemes = Vec::new();
+    
+    for _ in 0..count {
+        let mut lexeme = quote_lexeme(reader.cstr()?);
+        let npos = reader.u16()?;
+        let positions = (0..npos)
+            .map(|_| reader.u16().map(|p| {
+                let weight = match p >> 14 {
+                    3 => "A",
+                    2 => "B",
+                    1 => "C",
+                    _ => "",
+                };
+                format!("{}{}", p & 0x3FFF, weight)
+            }))
+            .collect::<Result<Vec<_>, _>>()?;
+        if !positions.is_empty() {
+            lexeme.push(':');
+            lexeme.push_str(&positions.join(","));
+        }
+        lexemes.push(lexeme);
+    }
+    
+    Ok(lexemes.join(" "))
+}
+
+/// Decode a binary tsquery (operators in prefix order) into infix text
+fn decode_pg_tsquery(raw: &[u8]) -> Result<String, anyhow::Error> {
+    enum Item {
+        Operand(String),
+        Not,
+        And,
+        Or,
+        Phrase(u16),
+    }
+    
+    fn build(items: &mut std::vec::IntoIter<Item>) -> Result<String, anyhow::Error> {
+        let item = items.next().ok_or_else(|| anyhow::anyhow!("Truncated tsquery"))?;
+        let wrap = |s: String| if s.contains(' ') { format!("( {} )", s) } else { s };
+        Ok(match item {
+            Item::Operand(operand) => operand,
+            Item::Not => format!("!{}", wrap(build(items)?)),
+            binary => {
+                // The right operand is stored first
+                let right = wrap(build(items)?);
+                let left = wrap(build(items)?);
+                let op = match binary {
+                    Item::And => "&".to_string(),
+                    Item::Or => "|".to_string(),
+                    Item::Phrase(1) => "<->".to_string(),
+                    Item::Phrase(distance) => format!("<{}>", distance),
+                    _ => unreachable!(),
+                };
+                format!("{} {} {}", left, op, right)
+            },
+        })
+    }
+    
+    let mut reader = ByteReader::new(raw);
+    let count = reader.i32()?;
+    if count == 0 {
+        return Ok(String::new());
+    }
+    
+    let mut items = Vec::new();
+    for _ in 0..count {
+        match reader.u8()? {
+            1 => {
+                let weight = reader.u8()?;
+                let prefix = reader.u8()? != 0;
+                let mut operand = quote_lexeme(reader.cstr()?);
+                if weight != 0 || prefix {
+                    operand.push(':');
+                    if prefix {
+                        operand.push('*');
+                    }
+                    for (bit, letter) in [(8, 'A'), (4, 'B'), (2, 'C'), (1, 'D')] {
+                        if weight & bit != 0 {
+                            operand.push(letter);
+                        }
+                    }
+                }
+                items.push(Item::Operand(operand));
+            },
+            2 => items.push(match reader.u8()? {
+                1 => Item::Not,
+                2 => Item::And,
+                3 => Item::Or,
+                4 => Item::Phrase(reader.u16()?),
+                other => return Err(anyhow::anyhow!("Unknown tsquery operator {}", other)),
+            }),
+            other => return Err(anyhow::anyhow!("Unknown tsquery item type {}", other)),
+        }
+    }
+    
+    let mut iter = items.into_iter();
+    build(&mut iter)
+}
+
+/// A query parameter from the --params JSON array. Values are encoded for the
+/// parameter type the server infers when the statement is prepared; the
+/// `{"type": "uuid", "value": "..."}` form pins the type explicitly.
+#[derive(Debug)]
+struct JsonParam {
+    value: Value,
+    type_name: Option<String>,
+}
+
+impl ToSql for JsonParam {
+    fn to_sql(&self, ty: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        encode_pg_param(&self.value, ty, out).map_err(Into::into)
+    }
+    
+    fn accepts(_ty: &Type) -> bool {
+        true
+    }
+    
+    to_sql_checked!();
+}
+
+fn json_to_sql_params(params: &Value) -> Result<Vec<JsonParam>, anyhow::Error> {
+    match params {
+        Value::Array(arr) => {
+            let mut sql_params = Vec::new();
+            
+            for value in arr {
+                match value {
+                    Value::Object(obj) if obj.len() == 2 && obj.contains_key("type") && obj.contains_key("value") => {
+                        let type_name = obj["type"].as_str()
+                            .ok_or_else(|| anyhow::anyhow!("Parameter 'type' must be a string"))?;
+                        sql_params.push(JsonParam {
+                            value: obj["value"].clone(),
+                            type_name: Some(type_name.to_string()),
+                        });
+                    },
+                    _ => sql_params.push(JsonParam {
+                        value: value.clone(),
+                        type_name: None,
+                    }),
+                }
+            }
+            
+            Ok(sql_params)
+        },
+        _ => Err(anyhow::anyhow!("Parameters must be a JSON array")),
+    }
+}
+
+/// Column values from mutate data. Unlike --params these are taken literally,
+/// so a JSONB value shaped like `{"type": ..., "value": ...}` is stored as is
+fn data_to_sql_params(values: impl IntoIterator<Item = Value>) -> Vec<JsonParam> {
+    values.into_iter()
+        .map(|value| JsonParam { value, type_name: None })
+        .collect()
+}
+
+/// Borrow parameters in the form expected by tokio-postgres query methods
+fn sql_param_refs(params: &[JsonParam]) -> Vec<&(dyn ToSql + Sync)> {
+    params.iter()
+        .map(|p| p as &(dyn ToSql + Sync))
+        .collect()
+}
+
+/// Look up a type by name (e.g. `uuid`, `numeric`, `text[]`, or a user-defined enum)
+async fn resolve_pg_type<C: tokio_postgres::GenericClient>(
+    client: &C,
+    name: &str,
+) -> Result<Type, anyhow::Error> {
+    let row = client.query_opt(
+        "SELECT t.oid, t.typname::text, t.typtype::text, n.nspname::text
+         FROM pg_type t
+         JOIN pg_namespace n ON n.oid = t.typnamespace
+         WHERE t.oid = to_regtype($1)",
+        &[&name],
+    ).await?
+        .ok_or_else(|| anyhow::anyhow!("Unknown parameter type '{}'", name))?;
+    
+    let oid: u32 = row.get(0);
+    if let Some(ty) = Type::from_oid(oid) {
+        return Ok(ty);
+    }
+    
+    let typtype: String = row.get(2);
+    let kind = if typtype == "e" { Kind::Enum(Vec::new()) } else { Kind::Simple };
+    Ok(Type::new(row.get(1), oid, kind, row.get(3)))
+}
+
+/// Prepare a statement, pinning explicitly typed parameters and letting the
+/// server infer the rest
+async fn prepare_statement<C: tokio_postgres::GenericClient>(
+    client: &C,
+    query: &str,
+    params: &[JsonParam],
+) -> Result<Statement, anyhow::Error> {
+    // OID 0 leaves a parameter's type unspecified
+    let unspecified = Type::new("unspecified".to_string(), 0, Kind::Simple, "pg_catalog".to_string());
+    
+    let mut types = Vec::new();
+    for param in params {
+        types.push(match param.type_name {
+            Some(ref name) => resolve_pg_type(client, name).await?,
+            None => unspecified.clone(),
+        });
+    }
+    while types.last().is_some_and(|t| t.oid() == 0) {
+        types.pop();
+    }
+    
+    let statement = client.prepare_typed(query, &types).await?;
+    
+    if statement.params().len() != params.len() {
+        return Err(anyhow::anyhow!("Query expects {} parameters but {} were given",
+            statement.params().len(), params.len()));
+    }
+    
+    Ok(statement)
+}
+
+fn param_as_str<'a>(value: &'a Value, ty: &Type) -> Result<&'a str, anyhow::Error> {
+    value.as_str()
+        .ok_or_else(|| anyhow::anyhow!("Expected a string for parameter of type {}, got {}", ty.name(), value))
+}
+
+fn param_as_i64(value: &Value, ty: &Type) -> Result<i64, anyhow::Error> {
+    match value {
+        Value::Number(n) => n.as_i64()
+            .ok_or_else(|| anyhow::anyhow!("Expected an integer for parameter of type {}, got {}", ty.name(), n)),
+        Value::String(s) => s.trim().parse()
+            .map_err(|_| anyhow::anyhow!("Expected an integer for parameter of type {}, got '{}'", ty.name(), s)),
+        _ => Err(anyhow::anyhow!("Expected an integer for parameter of type {}, got {}", ty.name(), value)),
+    }
+}
+
+fn param_as_f64(value: &Value, ty: &Type) -> Result<f64, anyhow::Error> {
+    match value {
+        Value::Number(n) => n.as_f64()
+            .ok_or_else(|| anyhow::anyhow!("Invalid number parameter")),
+        Value::String(s) => match s.trim() {
+            "NaN" => Ok(f64::NAN),
+            "Infinity" | "infinity" => Ok(f64::INFINITY),
+            "-Infinity" | "-infinity" => Ok(f64::NEG_INFINITY),
+            other => other.parse()
+                .map_err(|_| anyhow::anyhow!("Expected a number for parameter of type {}, got '{}'", ty.name(), s)),
+        },
+        _ => Err(anyhow::anyhow!("Expected a number for parameter of type {}, got {}", ty.name(), value)),
+    }
+}
+
+/// Read a vector from a JSON array of numbers, or a string of
+/// comma-separated floats (optionally bracketed) as the qdrant tool takes them
+fn parse_vector_param(value: &Value) -> Result<Vec<f32>, anyhow::Error> {
+    match value {
+        Value::Array(elements) => elements.iter()
+            .map(|element| element.as_f64()
+                .map(|element| element as f32)
+                .ok_or_else(|| anyhow::anyhow!("Vector elements must be numbers, got {}", element)))
+            .collect(),
+        Value::String(s) => s.trim().trim_start_matches('[').trim_end_matches(']')
+            .split(',')
+            .map(|element| element.trim().parse::<f32>()
+                .map_err(|_| anyhow::anyhow!("Invalid vector element '{}'", element.trim())))
+            .collect(),
+        _ => Err(anyhow::anyhow!("Expected a vector as a JSON array or comma-separated floats, got {}", value)),
+    }
+}
+
+fn param_i32(value: i64, ty: &Type) -> Result<i32, anyhow::Error> {
+    i32::try_from(value)
+        .map_err(|_| anyhow::anyhow!("Value {} out of range for parameter of type {}", value, ty.name()))
+}
+
+fn parse_param_timestamp(s: &str) -> Result<chrono::NaiveDateTime, anyhow::Error> {
+    let s = s.trim();
+    if let Ok(ts) = chrono::DateTime::parse_from_rfc3339(s) {
+        return Ok(ts.naive_utc());
+    }
+    for format in ["%Y-%m-%d %H:%M:%S%.f%#z", "%Y-%m-%dT%H:%M:%S%.f%#z"] {
+        if let Ok(ts) = chrono::DateTime::parse_from_str(s, format) {
+            return Ok(ts.naive_utc());
+        }
+    }
+    for format in ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"] {
+        if let Ok(ts) = chrono::NaiveDateTime::parse_from_str(s, format) {
+            return Ok(ts);
+        }
+    }
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap());
+    }
+    Err(anyhow::anyhow!("Invalid timestamp '{}'", s))
+}
+
+/// Encode a decimal string (optionally with an exponent) as a binary NUMERIC
+fn encode_pg_numeric(s: &str, out: &mut BytesMut) -> Result<(), anyhow::Error> {
+    let s = s.trim();
+    let special = match s {
+        "NaN" => Some(0xC000u16),
+        "Infinity" | "infinity" => Some(0xD000),
+        "-Infinity" | "-infinity" => Some(0xF000),
+        _ => None,
+    };
+    if let Some(sign) = special {
+        out.put_i16(0);
+        out.put_i16(0);
+        out.put_u16(sign);
+        out.put_u16(0);
+        return Ok(());
+    }
+    
+    let invalid = || anyhow::anyhow!("Invalid numeric value '{}'", s);
+    let (negative, unsigned) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(pos) => (&unsigned[..pos], unsigned[pos + 1..].parse::<i64>().map_err(|_| invalid())?),
+        None => (unsigned, 0),
+    };
+    let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if int_part.is_empty() && frac_part.is_empty()
+        || !int_part.chars().chain(frac_part.chars()).all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    
+    // Shift the decimal point by the exponent
+    let digits: String = format!("{}{}", int_part, frac_part);
+    let point = int_part.len() as i64 + exponent;
+    let (int_digits, frac_digits) = if point <= 0 {
+        (String::new(), format!("{}{}", "0".repeat((-point) as usize), digits))
+    } else if point as usize >= digits.len() {
+        (format!("{}{}", digits, "0".repeat(point as usize - digits.len())), String::new())
+    } else {
+        (digits[..point as usize].to_string(), digits[point as usize..].to_string())
+    };
+    let dscale = frac_digits.len();
+    
+    // Regroup into base-10000 digits aligned on the decimal point
+    let int_padded = format!("{}{}", "0".repeat((4 - int_digits.len() % 4) % 4), int_digits);
+    let frac_padded = format!("{}{}", frac_digits, "0".repeat((4 - frac_digits.len() % 4) % 4));
+    let mut groups: Vec<i16> = int_padded.as_bytes()
+        .chunks(4)
+        .chain(frac_padded.as_bytes().chunks(4))
+        .map(|chunk| std::str::from_utf8(chunk).unwrap().parse().unwrap())
+        .collect();
+    let mut weight = (int_padded.len() / 4) as i32 - 1;
+    
+    let leading_zeros = groups.iter().take_while(|&&g| g == 0).count();
+    groups.drain(..leading_zeros);
+    weight -= leading_zeros as i32;
+    while groups.last() == Some(&0) {
+        groups.pop();
+    }
+    if groups.is_empty() {
+        weight = 0;
+    }
+    
+    out.put_i16(groups.len() as i16);
+    out.put_i16(weight as i16);
+    out.put_u16(if negative && !groups.is_empty() { 0x4000 } else { 0x0000 });
+    out.put_u16(dscale as u16);
+    for group in groups {
+        out.put_i16(group);
+    }
+    
+    Ok(())
+}
+
+/// Parse an ISO 8601 duration (as produced for INTERVAL columns) into months, days and microseconds
+fn parse_param_interval(s: &str) -> Result<(i32, i32, i64), anyhow::Error> {
+    let invalid = || anyhow::anyhow!("Invalid interval '{}', expected an ISO 8601 duration like P1DT2H", s);
+    let body = s.trim().strip_prefix('P').ok_or_else(invalid)?;
+    
+    let (mut months, mut days, mut micros) = (0i64, 0i64, 0i64);
+    let mut in_time = false;
+    let mut number = String::new();
+    
+    for c in body.chars() {
+        match c {
+            'T' => in_time = true,
+            '0'..='9' | '.' | '-' | '+' => number.push(c),
+            unit => {
+                let value: f64 = number.parse().map_err(|_| invalid())?;
+                number.clear();
+                match (in_time, unit) {
+                    (false, 'Y') => months += (value * 12.0) as i64,
+                    (false, 'M') => months += value as i64,
+                    (false, 'W') => days += (value * 7.0) as i64,
+                    (false, 'D') => days += value as i64,
+                    (true, 'H') => micros += (value * 3_600_000_000.0).round() as i64,
+                    (true, 'M') => micros += (value * 60_000_000.0).round() as i64,
+                    (true, 'S') => micros += (value * 1_000_000.0).round() as i64,
+                    _ => return Err(invalid()),
+                }
+            },
+        }
+    }
+    if !number.is_empty() {
+        return Err(invalid());
+    }
+    
+    Ok((i32::try_from(months)?, i32::try_from(days)?, micros))
+}
+
+/// Write a length-prefixed value into a composite binary buffer
+fn encode_pg_element(value: &Value, ty: &Type, out: &mut BytesMut) -> Result<bool, anyhow::Error> {
+    let mut element = BytesMut::new();
+    match encode_pg_param(value, ty, &mut element)? {
+        IsNull::Yes => {
+            out.put_i32(-1);
+            Ok(true)
+        },
+        IsNull::No => {
+            out.put_i32(element.len() as i32);
+            out.extend_from_slice(&element);
+            Ok(false)
+        },
+    }
+}
+
+fn encode_pg_array(value: &Value, element: &Type, out: &mut BytesMut) -> Result<(), anyhow::Error> {
+    // Walk the first element of each level to find the dimensions
+    let mut dims = Vec::new();
+    let mut level = value;
+    while let Value::Array(items) = level {
+        dims.push(items.len());
+        match items.first() {
+            // JSON elements may themselves be arrays, so only descend for other element types
+            Some(first @ Value::Array(_)) if *element != Type::JSON && *element != Type::JSONB => level = first,
+            _ => break,
+        }
+    }
+    
+    fn flatten<'a>(value: &'a Value, dims: &[usize], out: &mut Vec<&'a Value>) -> Result<(), anyhow::Error> {
+        match (value, dims.split_first()) {
+            (Value::Array(items), Some((&len, rest))) => {
+                if items.len() != len {
+                    return Err(anyhow::anyhow!("Multidimensional array parameters must be rectangular"));
+                }
+                for item in items {
+                    flatten(item, rest, out)?;
+                }
+                Ok(())
+            },
+            (_, None) => {
+                out.push(value);
+                Ok(())
+            },
+            _ => Err(anyhow::anyhow!("Multidimensional array parameters must be rectangular")),
+        }
+    }
+    
+    let mut elements = Vec::new();
+    flatten(value, &dims, &mut elements)?;
+    
+    let ndim = if elements.is_empty() { 0 } else { dims.len() };
+    let mut body = BytesMut::new();
+    let mut has_nulls = false;
+    for item in elements {
+        has_nulls |= encode_pg_element(item, element, &mut body)?;
+    }
+    
+    out.put_i32(ndim as i32);
+    out.put_i32(has_nulls as i32);
+    out.put_u32(element.oid());
+    for &len in dims.iter().take(ndim) {
+        out.put_i32(len as i32);
+        out.put_i32(1);
+    }
+    out.extend_from_slice(&body);
+    
+    Ok(())
+}
+
+/// Encode a JSON value as the binary form of `ty`
+/// Convert a normalized decimal amount to cents, rounding half away from zero
+/// on the third fractional digit like money's text input does
+fn money_cents(amount: &str) -> Result<i64, anyhow::Error> {
+    let out_of_range = || anyhow::anyhow!("Value {} out of range for type money", amount);
+    let (negative, digits) = match amount.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, amount),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let fraction = fraction.as_bytes();
+    let digit = |i: usize| fraction.get(i).map_or(0, |d| (d - b'0') as i128);
+    
+    // Work in i128 so the most negative amount doesn't overflow before the sign is applied
+    let whole: i128 = whole.parse().map_err(|_| out_of_range())?;
+    let cents = whole.checked_mul(100)
+        .and_then(|cents| cents.checked_add(digit(0) * 10 + digit(1) + i128::from(digit(2) >= 5)))
+        .ok_or_else(out_of_range)?;
+    i64::try_from(if negative { -cents } else { cents }).map_err(|_| out_of_range())
+}
+
+fn encode_pg_param(value: &Value, ty: &Type, out: &mut BytesMut) -> Result<IsNull, anyhow::Error> {
+    if value.is_null() {
+        return Ok(IsNull::Yes);
+    }
+    
+    match ty.kind() {
+        Kind::Array(element) => {
+            if !value.is_array() {
+                return Err(anyhow::anyhow!("Expected a JSON array for parameter of type {}", ty.name()));
+            }
+            encode_pg_array(value, element, out)?;
+            return Ok(IsNull::No);
+        },
+        Kind::Domain(base) => return encode_pg_param(value, base, out),
+        Kind::Enum(_) => {
+            out.extend_from_slice(param_as_str(value, ty)?.as_bytes());
+            return Ok(IsNull::No);
+        },
+        _ => {},
+    }
+    
+    match *ty {
+        Type::BOOL => {
+            let b = match value {
+                Value::Bool(b) => *b,
+                Value::String(s) if s.eq_ignore_ascii_case("true") => true,
+                Value::String(s) if s.eq_ignore_ascii_case("false") => false,
+                _ => return Err(anyhow::anyhow!("Expected a boolean for parameter of type bool, got {}", value)),
+            };
+            out.put_u8(b as u8);
+        },
+        Type::INT2 => {
+            let v = param_as_i64(value, ty)?;
+            out.put_i16(i16::try_from(v)
+                .map_err(|_| anyhow::anyhow!("Value {} out of range for parameter of type int2", v))?);
+        },
+        Type::INT4 => out.put_i32(param_i32(param_as_i64(value, ty)?, ty)?),
+        Type::INT8 => out.put_i64(param_as_i64(value, ty)?),
+        Type::OID | Type::XID | Type::CID | Type::REGPROC | Type::REGPROCEDURE | Type::REGOPER
+        | Type::REGOPERATOR | Type::REGCLASS | Type::REGTYPE | Type::REGCONFIG | Type::REGDICTIONARY
+        | Type::REGNAMESPACE | Type::REGROLE | Type::REGCOLLATION => {
+            let v = param_as_i64(value, ty)?;
+            out.put_u32(u32::try_from(v)
+                .map_err(|_| anyhow::anyhow!("Value {} out of range for parameter of type {}", v, ty.name()))?);
+        },
+        Type::FLOAT4 => out.put_f32(param_as_f64(value, ty)? as f32),
+        Type::FLOAT8 => out.put_f64(param_as_f64(value, ty)?),
+        Type::NUMERIC => match value {
+            Value::Number(n) => encode_pg_numeric(&n.to_string(), out)?,
+            _ => encode_pg_numeric(param_as_str(value, ty)?, out)?,
+        },
+        Type::MONEY => {
+            let mut numeric = BytesMut::new();
+            let text = match value {
+                Value::Number(n) => n.to_string(),
+                _ => param_as_str(value, ty)?.to_string(),
+            };
+            encode_pg_numeric(&text, &mut numeric)?;
+            out.put_i64(money_cents(&decode_pg_numeric(&numeric)?)?);
+        },
+        Type::CHAR => {
+            let s = param_as_str(value, ty)?;
+            out.put_u8(*s.as_bytes().first().unwrap_or(&0));
+        },
+        Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME | Type::UNKNOWN | Type::XML | Type::REFCURSOR => {
+            // Scalars are accepted for text parameters and sent in their JSON text form
+            match value {
+                Value::String(s) => out.extend_from_slice(s.as_bytes()),
+                other => out.extend_from_slice(other.to_string().as_bytes()),
+            }
+        },
+        Type::JSON => out.extend_from_slice(value.to_string().as_bytes()),
+        Type::JSONB => {
+            out.put_u8(1);
+            out.extend_from_slice(value.to_string().as_bytes());
+        },
+        Type::UUID => {
+            let uuid = uuid::Uuid::parse_str(param_as_str(value, ty)?)?;
+            out.extend_from_slice(uuid.as_bytes());
+        },
+        Type::BYTEA => {
+            let bytes = base64::engine::general_purpose::STANDARD.decode(param_as_str(value, ty)?)
+                .map_err(|e| anyhow::anyhow!("bytea parameters must be base64 encoded: {}", e))?;
+            out.extend_from_slice(&bytes);
+        },
+        Type::DATE => {
+            let s = param_as_str(value, ty)?.trim();
+            let days = match s {
+                "infinity" => i32::MAX,
+                "-infinity" => i32::MIN,
+                _ => {
+                    let date = chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                        .map_err(|_| anyhow::anyhow!("Invalid date '{}'", s))?;
+                    (date - pg_epoch().date()).num_days() as i32
+                },
+            };
+            out.put_i32(days);
+        },
+        Type::TIMESTAMP | Type::TIMESTAMPTZ => {
+            let micros = match value {
+                // Numbers are taken as Unix epoch seconds
+                Value::Number(_) => {
+                    let seconds = param_as_f64(value, ty)?;
+                    (seconds * 1_000_000.0).round() as i64 - PG_EPOCH_OFFSET_DAYS * 86_400_000_000
+                },
+                _ => match param_as_str(value, ty)?.trim() {
+                    "infinity" => i64::MAX,
+                    "-infinity" => i64::MIN,
+                    s => (parse_param_timestamp(s)? - pg_epoch()).num_microseconds()
+                        .ok_or_else(|| anyhow::anyhow!("Timestamp '{}' out of range", s))?,
+                },
+            };
+            out.put_i64(micros);
+        },
+        Type::TIME => {
+            let s = param_as_str(value, ty)?.trim();
+            let time = chrono::NaiveTime::parse_from_str(s, "%H:%M:%S%.f")
+                .or_else(|_| chrono::NaiveTime::parse_from_str(s, "%H:%M"))
+                .map_err(|_| anyhow::anyhow!("Invalid time '{}'", s))?;
+            out.put_i64((time - chrono::NaiveTime::MIN).num_microseconds().unwrap_or(0));
+        },
+        Type::INTERVAL => {
+            let (months, days, micros) = match value {
+                Value::Number(_) => (0, 0, (param_as_f64(value, ty)? * 1_000_000.0).round() as i64),
+                _ => parse_param_interval(param_as_str(value, ty)?)?,
+            };
+            out.put_i64(micros);
+            out.put_i32(days);
+            out.put_i32(months);
+        },
+        Type::INET | Type::CIDR => {
+            let s = param_as_str(value, ty)?.trim();
+            let (addr, bits) = match s.split_once('/') {
+                Some((addr, bits)) => (addr, Some(bits.parse::<u8>()
+                    .map_err(|_| anyhow::anyhow!("Invalid network prefix in '{}'", s))?)),
+                None => (s, None),
+            };
+            let ip: std::net::IpAddr = addr.parse()
+                .map_err(|_| anyhow::anyhow!("Invalid IP address '{}'", s))?;
+            let (family, max_bits, octets) = match ip {
+                std::net::IpAddr::V4(v4) => (2u8, 32u8, v4.octets().to_vec()),
+                std::net::IpAddr::V6(v6) => (3u8, 128u8, v6.octets().to_vec()),
+            };
+            out.put_u8(family);
+            out.put_u8(bits.unwrap_or(max_bits));
+            out.put_u8((*ty == Type::CIDR) as u8);
+            out.put_u8(octets.len() as u8);
+            out.extend_from_slice(&octets);
+        },
+        _ => match (ty.name(), value) {
+            ("citext", Value::String(s)) => out.extend_from_slice(s.as_bytes()),
+            ("vector", _) => {
+                let elements = parse_vector_param(value)?;
+                let dimensions = u16::try_from(elements.len())
+                    .map_err(|_| anyhow::anyhow!("Vector has too many dimensions ({})", elements.len()))?;
+                out.put_u16(dimensions);
+                out.put_u16(0);
+                for element in elements {
+                    out.put_f32(element);
+                }
+            },
+            _ => return Err(anyhow::anyhow!(
+                "Can't bind parameter of type {}; cast the placeholder (e.g. $1::text::{}) or pass {{\"type\": \"text\", \"value\": ...}}",
+                ty.name(), ty.name()
+            )),
+        },
+    }
+    
+    Ok(IsNull::No)
+}
+
+/// Quote an SQL identifier, escaping embedded double quotes
+fn quote_ident(ident: &str) -> String {
+    format!("\"{}\"", ident.replace('"', "\"\""))
+}
+
+/// Split a possibly schema-qualified name on the dots outside double quotes.
+/// Quoted parts are unquoted, so `"my.schema".t` gives `my.schema` and `t`
+fn split_qualified_name(name: &str) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = name.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        let part = parts.last_mut().expect("parts is never empty");
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                part.push('"');
+            },
+            '"' => quoted = !quoted,
+            '.' if !quoted => parts.push(String::new()),
+            c => part.push(c),
+        }
+    }
+    parts
+}
+
+/// Quote a possibly schema-qualified name, quoting each part
+fn quote_qualified_ident(name: &str) -> String {
+    split_qualified_name(name)
+        .iter()
+        .map(|part| quote_ident(part))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Quote an SQL string literal, escaping embedded single quotes
+fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Append a LIMIT clause unless the query already has one
+fn apply_select_limit(mut query: String, limit: Option<i64>) -> String {
+    if let Some(limit_val) = limit {
+        if !query.to_uppercase().contains("LIMIT") {
+            query.push_str(&format!(" LIMIT {}", limit_val));
+        }
+    }
+    query
+}
+
+async fn handle_select_command(
+    pool: &Pool,
+    query: String,
+    params_str: String,
+    limit: Option<i64>,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let params_json: Value = parse_json_arg(&params_str, "parameters")?;
+    let sql_params = json_to_sql_params(&params_json)?;
+    let final_query = apply_select_limit(query, limit);
+    
+    let client = pool.get().await?;
+    let statement = prepare_statement(&**client, &final_query, &sql_params).await?;
+    let rows = client.query(&statement, &sql_param_refs(&sql_params)).await?;
+    
+    let mut results = Vec::new();
+    for row in rows {
+        results.push(row_to_json(&row)?);
+    }
+    
+    let result_json = Value::Array(results);
+    println!("{}", format_output(&result_json, options.format));
+    
+    Ok(())
+}
+
+/// Stream query results row by row through a server-side cursor
+async fn handle_select_stream(
+    pool: &Pool,
+    query: String,
+    params_str: String,
+    limit: Option<i64>,
+    fetch_size: i64,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    if fetch_size <= 0 {
+        return Err(anyhow::anyhow!("--fetch-size must be positive"));
+    }
+    let params_json: Value = parse_json_arg(&params_str, "parameters")?;
+    let sql_params = json_to_sql_params(&params_json)?;
+    let final_query = apply_select_limit(query, limit);
+    
+    let mut client = pool.get().await?;
+    let transaction = client.transaction().await?;
+    
+    // Cursors only live inside a transaction; preparing the bare query first
+    // gives us the column names for the CSV header even when no rows come back
+    let columns: Vec<String> = prepare_statement(&*transaction, &final_query, &sql_params).await?
+        .columns()
+        .iter()
+        .map(|column| column.name().to_string())
+        .collect();
+    let declare = format!("DECLARE _code_tools_stream NO SCROLL CURSOR FOR {}", final_query);
+    let statement = prepare_statement(&*transaction, &declare, &sql_params).await?;
+    transaction.execute(&statement, &sql_param_refs(&sql_params)).await?;
+    
+    let csv = matches!(options.format, OutputFormat::Csv);
+    let stdout = std::io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    if csv {
+        let header: Vec<Value> = columns.iter().map(|name| Value::String(name.clone())).collect();
+        writeln!(out, "{}", format_csv_row(&header))?;
+    }
+    
+    let fetch = format!("FETCH FORWARD {} FROM _code_tools_stream", fetch_size);
+    let streamed: Result<(), anyhow::Error> = async {
+        loop {
+            let rows = transaction.query(fetch.as_str(), &[]).await?;
+            for row in &rows {
+                if csv {
+                    let values = (0..row.len())
+                        .map(|i| postgres_value_to_json(row, i, row.columns()[i].type_()))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    writeln!(out, "{}", format_csv_row(&values))?;
+                } else {
+                    writeln!(out, "{}", row_to_json(row)?)?;
+                }
+            }
+            out.flush()?;
+            if (rows.len() as i64) < fetch_size {
+                return Ok(());
+            }
+        }
+    }.await;
+    
+    // A reader that stops early (e.g. `| head`) is not an error
+    match streamed {
+        Err(e) if e.downcast_ref::<std::io::Error>().is_some_and(|e| e.kind() == std::io::ErrorKind::BrokenPipe) => Ok(()),
+        Err(e) => Err(e),
+        Ok(()) => {
+            transaction.commit().await?;
+            Ok(())
+        }
+    }
+}
+
+/// Per-node figures pulled out of an EXPLAIN (FORMAT JSON) plan
+#[derive(Debug)]
+struct PlanNodeSummary {
+    id: usize,
+    depth: usize,
+    node_type: String,
+    /// Schema of the scanned relation, reported by EXPLAIN VERBOSE
+    schema: Option<String>,
+    relation: Option<String>,
+    alias: Option<String>,
+    index: Option<String>,
+    filter: Option<String>,
+    total_cost: f64,
+    /// Cost of this node minus the cost of its direct children
+    exclusive_cost: f64,
+    plan_rows: f64,
+    actual_rows: Option<f64>,
+    loops: Option<f64>,
+    /// Time spent in this node across all loops, excluding children
+    exclusive_time_ms: Option<f64>,
+    rows_removed_by_filter: Option<f64>,
+}
+
+impl PlanNodeSummary {
+    fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "depth": self.depth,
+            "node_type": self.node_type,
+            "schema": self.schema,
+            "relation": self.relation,
+            "alias": self.alias,
+            "index": self.index,
+            "filter": self.filter,
+            "total_cost": self.total_cost,
+            "exclusive_cost": self.exclusive_cost,
+            "plan_rows": self.plan_rows,
+            "actual_rows": self.actual_rows,
+            "loops": self.loops,
+            "exclusive_time_ms": self.exclusive_time_ms,
+            "rows_removed_by_filter": self.rows_removed_by_filter,
+        })
+    }
+    
+    /// Ratio between estimated and actual rows per loop, with the direction of the miss
+    fn row_estimate_error(&self) -> Option<(f64, &'static str)> {
+        let actual = self.actual_rows?;
+        if self.loops == Some(0.0) {
+            return None;
+        }
+        let (high, low) = if actual > self.plan_rows { (actual, self.plan_rows) } else { (self.plan_rows, actual) };
+        let direction = if actual > self.plan_rows { "underestimate" } else { "overestimate" };
+        Some((high / low.max(1.0), direction))
+    }
+}
+
+/// Round to three decimals, the precision EXPLAIN reports timings with
+fn round_millis(value: f64) -> f64 {
+    (value * 1000.0).round() / 1000.0
+}
+
+/// Flatten a JSON plan tree into pre-order node summaries
+fn summarize_plan(plan: &Value, depth: usize, out: &mut Vec<PlanNodeSummary>) {
+    let number = |key: &str| plan.get(key).and_then(Value::as_f64);
+    let text = |key: &str| plan.get(key).and_then(Value::as_str).map(str::to_string);
+    let children = plan.get("Plans").and_then(Value::as_array).map(Vec::as_slice).unwrap_or(&[]);
+    
+    let total_cost = number("Total Cost").unwrap_or(0.0);
+    let children_cost: f64 = children.iter()
+        .filter_map(|child| child.get("Total Cost").and_then(Value::as_f64))
+        .sum();
+    
+    // Actual Total Time is per loop, so scale by loops before subtracting children
+    let loops = number("Actual Loops");
+    let exclusive_time_ms = number("Actual Total Time").map(|time| {
+        let children_time: f64 = children.iter()
+            .map(|child| {
+                let time = child.get("Actual Total Time").and_then(Value::as_f64).unwrap_or(0.0);
+                let loops = child.get("Actual Loops").and_then(Value::as_f64).unwrap_or(1.0);
+                time * loops
+            })
+            .sum();
+        round_millis((time * loops.unwrap_or(1.0) - children_time).max(0.0))
+    });
+    
+    out.push(PlanNodeSummary {
+        id: out.len(),
+        depth,
+        node_type: text("Node Type").unwrap_or_default(),
+        schema: text("Schema"),
+        relation: text("Relation Name"),
+        alias: text("Alias"),
+        index: text("Index Name"),
+        filter: text("Filter"),
+        total_cost,
+        exclusive_cost: round_millis((total_cost - children_cost).max(0.0)),
+        plan_rows: number("Plan Rows").unwrap_or(0.0),
+        actual_rows: number("Actual Rows"),
+        loops,
+        exclusive_time_ms,
+        rows_removed_by_filter: number("Rows Removed by Filter"),
+    });
+    
+    for child in children {
+        summarize_plan(child, depth + 1, out);
+    }
+}
+
+async fn handle_explain_command(
+    pool: &Pool,
+    query: String,
+    params_str: String,
+    explain_options: &ExplainOptions,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let params_json: Value = parse_json_arg(&params_str, "parameters")?;
+    let sql_params = json_to_sql_params(&params_json)?;
+    
+    let ExplainOptions { analyze, buffers, top, seq_scan_rows, misestimate_factor } = *explain_options;
+    
+    // VERBOSE reports each scanned relation's schema, needed to look up its size
+    let mut flags = vec!["FORMAT JSON", "VERBOSE"];
+    if analyze {
+        flags.push("ANALYZE");
+    }
+    if buffers {
+        flags.push("BUFFERS");
+    }
+    let explain_query = format!("EXPLAIN ({}) {}", flags.join(", "), query);
+    
+    // ANALYZE really runs the statement, so keep any writes from sticking
+    let mut client = pool.get().await?;
+    let transaction = client.transaction().await?;
+    let statement = prepare_statement(&*transaction, &explain_query, &sql_params).await?;
+    let rows = transaction.query(&statement, &sql_param_refs(&sql_params)).await?;
+    transaction.rollback().await?;
+    
+    let explain: Value = rows.first()
+        .ok_or_else(|| anyhow::anyhow!("EXPLAIN returned no plan"))?
+        .try_get(0)?;
+    let output = explain.get(0).ok_or_else(|| anyhow::anyhow!("Unexpected EXPLAIN output: {}", explain))?;
+    let root = output.get("Plan").ok_or_else(|| anyhow::anyhow!("EXPLAIN output has no Plan"))?;
+    
+    let mut nodes = Vec::new();
+    summarize_plan(root, 0, &mut nodes);
+    
+    let mut slowest: Vec<&PlanNodeSummary> = nodes.iter().collect();
+    if analyze {
+        slowest.sort_by(|a, b| b.exclusive_time_ms.unwrap_or(0.0).total_cmp(&a.exclusive_time_ms.unwrap_or(0.0)));
+    } else {
+        slowest.sort_by(|a, b| b.exclusive_cost.total_cmp(&a.exclusive_cost));
+    }
+    let slowest_nodes: Vec<Value> = slowest.iter().take(top).map(|node| node.to_json()).collect();
+    
+    // Look up table sizes for every sequentially scanned relation
+    let mut seq_scans = Vec::new();
+    for node in nodes.iter().filter(|node| node.node_type == "Seq Scan") {
+        let Some(relation) = &node.relation else { continue };
+        let qualified_name = match &node.schema {
+            Some(schema) => format!("{}.{}", quote_ident(schema), quote_ident(relation)),
+            None => quote_ident(relation),
+        };
+        let reltuples: Option<f64> = client.query_opt(
+            "SELECT reltuples::float8 FROM pg_class WHERE oid = to_regclass($1)",
+            &[&qualified_name],
+        ).await?.map(|row| row.get(0));
+        // reltuples is -1 until the table has been vacuumed or analyzed
+        let estimated_rows = reltuples.filter(|rows| *rows >= 0.0).unwrap_or(node.plan_rows);
+        if estimated_rows >= seq_scan_rows {
+            let mut entry = node.to_json();
+            entry["table_rows"] = json!(estimated_rows);
+            seq_scans.push(entry);
+        }
+    }
+    
+    let row_estimate_mismatches: Vec<Value> = nodes.iter()
+        .filter_map(|node| {
+            let (factor, direction) = node.row_estimate_error()?;
+            if factor < misestimate_factor {
+                return None;
+            }
+            let mut entry = node.to_json();
+            entry["factor"] = json!(factor);
+            entry["direction"] = json!(direction);
+            Some(entry)
+        })
+        .collect();
+    
+    let mut result = json!({
+        "analyze": analyze,
+        "total_cost": root.get("Total Cost"),
+        "startup_cost": root.get("Startup Cost"),
+        "estimated_rows": root.get("Plan Rows"),
+        "actual_rows": root.get("Actual Rows"),
+        "planning_time_ms": output.get("Planning Time"),
+        "execution_time_ms": output.get("Execution Time"),
+        "node_count": nodes.len(),
+        "slowest_nodes": slowest_nodes,
+        "seq_scans": seq_scans,
+        "row_estimate_mismatches": row_estimate_mismatches,
+        "plan": output,
+    });
+    
+    if buffers {
+        let block = |key: &str| root.get(key).cloned().unwrap_or(Value::Null);
+        result["buffers"] = json!({
+            "shared_hit": block("Shared Hit Blocks"),
+            "shared_read": block("Shared Read Blocks"),
+            "shared_dirtied": block("Shared Dirtied Blocks"),
+            "temp_read": block("Temp Read Blocks"),
+            "temp_written": block("Temp Written Blocks"),
+        });
+    }
+    
+    println!("{}", format_output(&result, options.format));
+    
+    Ok(())
+}
+
+async fn handle_profile_command(
+    pool: &Pool,
+    table: String,
+    sample: Option<f64>,
+    estimate: bool,
+    top: i64,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    if let Some(percent) = sample {
+        if !(percent > 0.0 && percent <= 100.0) {
+            return Err(anyhow::anyhow!("--sample must be a percentage between 0 and 100"));
+        }
+    }
+    
+    let client = pool.get().await?;
+    let table_sql = quote_qualified_ident(&table);
+    let columns = client.query(
+        "SELECT a.attname::text, format_type(a.atttypid, a.atttypmod)
+         FROM pg_attribute a
+         WHERE a.attrelid = to_regclass($1) AND a.attnum > 0 AND NOT a.attisdropped
+         ORDER BY a.attnum",
+        &[&table_sql],
+    ).await?;
+    if columns.is_empty() {
+        return Err(anyhow::anyhow!("Table '{}' not found", table));
+    }
+    
+    // A shared seed makes every query below see the same sampled pages
+    let source = match sample {
+        Some(percent) => format!(
+            "{} TABLESAMPLE SYSTEM ({}) REPEATABLE ({})",
+            table_sql, percent, chrono::Utc::now().timestamp_subsec_nanos()
+        ),
+        None => table_sql.clone(),
+    };
+    
+    // Not every type has min/max or can be compared for DISTINCT (json, point,
+    // ...). Preparing a probe statement checks that without reading the table
+    let mut aggregates = vec!["count(*)".to_string()];
+    let mut profiles = Vec::new();
+    for row in &columns {
+        let name: String = row.get(0);
+        let type_name: String = row.get(1);
+        let column_sql = quote_ident(&name);
+        
+        // Booleans sort but have no min/max aggregates
+        let (min_sql, max_sql) = if type_name == "boolean" {
+            (format!("bool_and({})", column_sql), format!("bool_or({})", column_sql))
+        } else {
+            (format!("min({})", column_sql), format!("max({})", column_sql))
+        };
+        let orderable = client.prepare(&format!("SELECT {}, {} FROM {}", min_sql, max_sql, table_sql)).await.is_ok();
+        let groupable = client.prepare(&format!("SELECT count(DISTINCT {}) FROM {}", column_sql, table_sql)).await.is_ok();
+        
+        let first_index = aggregates.len();
+        aggregates.push(format!("count({})", column_sql));
+        aggregates.push(format!("round(avg(pg_column_size({})), 2)::float8", column_sql));
+        if groupable && !estimate {
+            aggregates.push(format!("count(DISTINCT {})", column_sql));
+        }
+        if orderable {
+            aggregates.push(min_sql);
+            aggregates.push(max_sql);
+        }
+        
+        profiles.push((name, type_name, column_sql, first_index, orderable, groupable));
+    }
+    
+    let stats_row = client.query_one(&format!("SELECT {} FROM {}", aggregates.join(", "), source), &[]).await?;
+    let total: i64 = stats_row.get(0);
+    
+    let (pg_stats, reltuples) = if estimate {
+        let relation = client.query_one(
+            "SELECT n.nspname::text, c.relname::text, greatest(c.reltuples, 0)::float8
+             FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace
+             WHERE c.oid = to_regclass($1)",
+            &[&table_sql],
+        ).await?;
+        let (schema, relation_name): (String, String) = (relation.get(0), relation.get(1));
+        let stats = client.query(
+            "SELECT attname::text, n_distinct::float8, most_common_vals::text::text[], most_common_freqs::float8[]
+             FROM pg_stats WHERE schemaname = $1 AND tablename = $2
+             ORDER BY inherited",
+            &[&schema, &relation_name],
+        ).await?;
+        (stats, relation.get::<_, f64>(2))
+    } else {
+        (Vec::new(), 0.0)
+    };
+    
+    let mut results = Vec::new();
+    for (name, type_name, column_sql, first_index, orderable, groupable) in profiles {
+        let non_null: i64 = stats_row.get(first_index);
+        let avg_width: Option<f64> = stats_row.get(first_index + 1);
+        let mut next_index = first_index + 2;
+        
+        let mut distinct = Value::Null;
+        let mut most_common = Value::Null;
+        if estimate {
+            if let Some(stats) = pg_stats.iter().find(|stats| stats.get::<_, String>(0) == name) {
+                // Negative n_distinct is a fraction of the row count, zero means unknown
+                let n_distinct: f64 = stats.get(1);
+                if n_distinct < 0.0 {
+                    distinct = json!((-n_distinct * reltuples).round() as i64);
+                } else if n_distinct > 0.0 {
+                    distinct = json!(n_distinct as i64);
+                }
+                
+                let values: Option<Vec<Option<String>>> = stats.get(2);
+                let frequencies: Option<Vec<f64>> = stats.get(3);
+                most_common = json!(values.unwrap_or_default().into_iter()
+                    .zip(frequencies.unwrap_or_default())
+                    .take(top.max(0) as usize)
+                    .map(|(value, frequency)| json!({ "value": value, "frequency": frequency }))
+                    .collect::<Vec<_>>());
+            }
+        } else if groupable {
+            let count: i64 = stats_row.get(next_index);
+            distinct = json!(count);
+            next_index += 1;
+            
+            let rows = client.query(&format!(
+                "SELECT {0}::text, count(*) FROM {1} WHERE {0} IS NOT NULL GROUP BY {0} ORDER BY count(*) DESC, 1 LIMIT $1",
+                column_sql, source
+            ), &[&top]).await?;
+            most_common = json!(rows.iter()
+                .map(|row| json!({ "value": row.get::<_, String>(0), "count": row.get::<_, i64>(1) }))
+                .collect::<Vec<_>>());
+        }
+        
+        let (min, max) = if orderable {
+            (
+                postgres_value_to_json(&stats_row, next_index, stats_row.columns()[next_index].type_())?,
+                postgres_value_to_json(&stats_row, next_index + 1, stats_row.columns()[next_index + 1].type_())?,
+            )
+        } else {
+            (Value::Null, Value::Null)
+        };
+        
+        let null_count = total - non_null;
+        results.push(json!({
+            "column": name,
+            "type": type_name,
+            "rows": total,
+            "null_count": null_count,
+            "null_ratio": if total > 0 { json!(null_count as f64 / total as f64) } else { Value::Null },
+            "distinct": distinct,
+            "min": min,
+            "max": max,
+            "avg_width": avg_width,
+            "most_common": most_common,
+        }));
+    }
+    
+    let result_json = Value::Array(results);
+    println!("{}", format_output(&result_json, options.format));
+    
+    Ok(())
+}
+
+/// Arguments of the vector-search command
+struct VectorSearch {
+    table: String,
+    column: String,
+    vector: String,
+    limit: i64,
+    metric: VectorMetric,
+    select: Option<String>,
+    where_clause: Option<String>,
+}
+
+async fn handle_vector_search(
+    pool: &Pool,
+    search: VectorSearch,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let vector = parse_vector_param(&Value::String(search.vector.clone()))?;
+    let client = pool.get().await?;
+    let table_sql = quote_qualified_ident(&search.table);
+    let column_sql = quote_ident(&search.column);
+    
+    let column_type: Option<String> = client.query_opt(
+        "SELECT format_type(a.atttypid, a.atttypmod)
+         FROM pg_attribute a
+         WHERE a.attrelid = to_regclass($1) AND a.attname = $2 AND NOT a.attisdropped",
+        &[&table_sql, &search.column],
+    ).await?.map(|row| row.get(0));
+    let column_type = column_type
+        .ok_or_else(|| anyhow::anyhow!("Column '{}' not found on table '{}'", search.column, search.table))?;
+    
+    // vector(n) columns must be searched with exactly n dimensions
+    if let Some(dimensions) = column_type.strip_prefix("vector(").and_then(|rest| rest.strip_suffix(')')) {
+        if dimensions.parse::<usize>().ok() != Some(vector.len()) {
+            return Err(anyhow::anyhow!(
+                "Query vector has {} dimensions but {} is {}",
+                vector.len(), search.column, column_type
+            ));
+        }
+    } else if !column_type.starts_with("vector") {
+        return Err(anyhow::anyhow!("Column '{}' is {}, not a pgvector vector", search.column, column_type));
+    }
+    
+    let select = match search.select {
+        Some(select) => select,
+        None => {
+            let rows = client.query(
+                "SELECT a.attname::text FROM pg_attribute a
+                 WHERE a.attrelid = to_regclass($1) AND a.attnum > 0 AND NOT a.attisdropped AND a.attname <> $2
+                 ORDER BY a.attnum",
+                &[&table_sql, &search.column],
+            ).await?;
+            rows.iter().map(|row| quote_ident(row.get(0))).collect::<Vec<_>>().join(", ")
+        },
+    };
+    
+    let query = format!(
+        "SELECT {0} {1} $1 AS _code_tools_distance{2} FROM {3}{4} ORDER BY {0} {1} $1 LIMIT $2",
+        column_sql,
+        search.metric.operator(),
+        if select.trim().is_empty() { String::new() } else { format!(", {}", select) },
+        table_sql,
+        search.where_clause.map(|w| format!(" WHERE {}", w)).unwrap_or_default(),
+    );
+    let params = vec![
+        JsonParam { value: json!(vector), type_name: Some("vector".to_string()) },
+        JsonParam { value: json!(search.limit), type_name: Some("int8".to_string()) },
+    ];
+    let statement = prepare_statement(&**client, &query, &params).await?;
+    let rows = client.query(&statement, &sql_param_refs(&params)).await?;
+    
+    let mut results = Vec::new();
+    for row in rows {
+        let mut fields = row_to_json(&row)?;
+        let distance = fields.as_object_mut()
+            .and_then(|obj| obj.remove("_code_tools_distance"))
+            .and_then(|distance| distance.as_f64())
+            .unwrap_or(f64::NAN);
+        results.push(json!({
+            "score": float_to_json(search.metric.score(distance)),
+            "distance": float_to_json(distance),
+            "row": fields,
+        }));
+    }
+    
+    let result_json = Value::Array(results);
+    println!("{}", format_output(&result_json, options.format));
+    
+    Ok(())
+}
+
+async fn handle_mutate_insert(
+    pool: &Pool,
+    table: String,
+    data_str: String,
+    returning: Option<String>,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let data_json: Value = parse_json_arg(&data_str, "data")?;
+    
+    let records = match data_json {
+        Value::Array(arr) => arr,
+        Value::Object(_) => vec![data_json],
+        _ => return Err(anyhow::anyhow!("Data must be JSON object or array of objects")),
+    };
+    
+    if records.is_empty() {
+        return Err(anyhow::anyhow!("No data to insert"));
+    }
+    
+    let client = pool.get().await?;
+    let results = insert_records(&**client, &table, records, returning.as_deref()).await?;
+    
+    let result_json = Value::Array(results);
+    println!("{}", format_output(&result_json, options.format));
+    
+    Ok(())
+}
+
+/// Insert records using the columns of the first one, preparing the statement once
+async fn insert_records<C: tokio_postgres::GenericClient>(
+    client: &C,
+    table: &str,
+    records: Vec<Value>,
+    returning: Option<&str>,
+) -> Result<Vec<Value>, anyhow::Error> {
+    let Some(Value::Object(first_record)) = records.first() else {
+        return Ok(Vec::new());
+    };
+    let columns: Vec<String> = first_record.keys().cloned().collect();
+    let placeholders: Vec<String> = (1..=columns.len()).map(|i| format!("${}", i)).collect();
+    
+    let mut insert_query = format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        table,
+        columns.join(", "),
+        placeholders.join(", ")
+    );
+    
+    if let Some(ret) = returning {
+        insert_query.push_str(&format!(" RETURNING {}", ret));
+    }
+    
+    let mut statement = None;
+    let mut results = Vec::new();
+    
+    for record in records {
+        if let Value::Object(record_obj) = record {
+            let sql_params = data_to_sql_params(columns.iter()
+                .map(|col| record_obj.get(col).cloned().unwrap_or(Value::Null)));
+            let param_refs = sql_param_refs(&sql_params);
+            
+            let statement = match statement {
+                Some(ref statement) => statement,
+                None => statement.insert(prepare_statement(client, &insert_query, &sql_params).await?),
+            };
+            
+            if returning.is_some() {
+                let rows = client.query(statement, &param_refs).await?;
+                for row in rows {
+                    results.push(row_to_json(&row)?);
+                }
+            } else {
+                client.execute(statement, &param_refs).await?;
+                results.push(json!({"inserted": true}));
+            }
+        }
+    }
+    
+    Ok(results)
+}
+
+async fn handle_mutate_update(
+    pool: &Pool,
+    table: String,
+    data_str: String,
+    where_clause: String,
+    returning: Option<String>,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let data_json: Value = parse_json_arg(&data_str, "data")?;
+    
+    let data_obj = match data_json {
+        Value::Object(obj) => obj,
+        _ => return Err(anyhow::anyhow!("Update data must be a JSON object")),
+    };
+    
+    if data_obj.is_empty() {
+        return Err(anyhow::anyhow!("No columns to update"));
+    }
+    
+    // Build SET clause with one placeholder per column, in key order
+    let set_clauses: Vec<String> = data_obj.keys()
+        .enumerate()
+        .map(|(i, col)| format!("{} = ${}", quote_ident(col), i + 1))
+        .collect();
+    
+    let mut update_query = format!(
+        "UPDATE {} SET {} WHERE {}",
+        table,
+        set_clauses.join(", "),
+        where_clause
+    );
+    
+    if let Some(ref ret) = returning {
+        update_query.push_str(&format!(" RETURNING {}", ret));
+    }
+    
+    let sql_params = data_to_sql_params(data_obj.values().cloned());
+    let param_refs = sql_param_refs(&sql_params);
+    
+    let client = pool.get().await?;
+    let statement = prepare_statement(&**client, &update_query, &sql_params).await?;
+    
+    if returning.is_some() {
+        let rows = client.query(&statement, &param_refs).await?;
+        let mut results = Vec::new();
+        for row in rows {
+            results.push(row_to_json(&row)?);
+        }
+        
+        let result = json!({
+            "affected_rows": results.len(),
+            "rows": results
+        });
+        println!("{}", format_output(&result, options.format));
+    } else {
+        let affected = client.execute(&statement, &param_refs).await?;
+        let result = json!({"affected_rows": affected});
+        println!("{}", format_output(&result, options.format));
+    }
+    
+    Ok(())
+}
+
+/// Display name of a column type, writing arrays as `element[]`
+fn pg_type_display(ty: &Type) -> String {
+    match ty.kind() {
+        Kind::Array(element) => format!("{}[]", element.name()),
+        _ => ty.name().to_string(),
+    }
+}
+
+async fn handle_mutate_delete(
+    pool: &Pool,
+    table: String,
+    where_clause: String,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let client = pool.get().await?;
+    let delete_query = format!("DELETE FROM {} WHERE {}", table, where_clause);
+    let affected = client.execute(&delete_query, &[]).await?;
+    
+    let result = json!({"affected_rows": affected});
+    println!("{}", format_output(&result, options.format));
+    
+    Ok(())
+}
+
+/// Run one statement and describe its outcome: timing, affected rows,
+/// column names and types, rows, and the notices it raised
+async fn run_execute_statement<C: tokio_postgres::GenericClient>(
+    client: &C,
+    messages: &mut tokio::sync::mpsc::UnboundedReceiver<tokio_postgres::AsyncMessage>,
+    sql: &str,
+    params: &[JsonParam],
+    expect_rows: bool,
+) -> Result<Value, anyhow::Error> {
+    let started = Instant::now();
+    let statement = prepare_statement(client, sql, params).await?;
+    let stream = client.query_raw(&statement, sql_param_refs(params)).await?;
+    pin_mut!(stream);
+    
+    let mut rows = Vec::new();
+    while let Some(row) = stream.next().await {
+        let row = row?;
+        if expect_rows {
+            rows.push(row_to_json(&row)?);
+        }
+    }
+    
+    let columns: Vec<Value> = statement.columns().iter()
+        .map(|column| json!({
+            "name": column.name(),
+            "type": pg_type_display(column.type_()),
+            "oid": column.type_().oid(),
+        }))
+        .collect();
+    
+    let mut result = json!({
+        "command": sql.split_whitespace().next().unwrap_or_default().to_uppercase(),
+        "affected_rows": stream.rows_affected(),
+        "duration_ms": started.elapsed().as_secs_f64() * 1000.0,
+        "notices": drain_notices(messages),
+    });
+    if !columns.is_empty() {
+        result["columns"] = Value::Array(columns);
+        if expect_rows {
+            result["rows"] = Value::Array(rows);
+        }
+    }
+    
+    Ok(result)
+}
+
+/// Structured form of a failed execute statement, with the server's details when available
+fn execute_error_to_json(error: &anyhow::Error) -> Value {
+    match error.downcast_ref::<tokio_postgres::Error>() {
+        Some(e) => statement_error_to_json(e),
+        None => json!({ "message": error.to_string() }),
+    }
+}
+
+/// Execute SQL on a dedicated connection so server notices can be reported.
+/// Several semicolon-separated statements give one result each. Without a
+/// transaction, statements before a failing one stay committed, so their
+/// results are still printed along with the error
+async fn handle_execute_command(
+    connection_config: &Config,
+    sql: String,
+    params_str: String,
+    transactional: bool,
+    expect_rows: bool,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let params_json: Value = parse_json_arg(&params_str, "parameters")?;
+    let sql_params = json_to_sql_params(&params_json)?;
+    
+    let statements = split_sql_script(&sql);
+    if statements.len() > 1 && !sql_params.is_empty() {
+        return Err(anyhow::anyhow!("Parameters can only be used with a single statement"));
+    }
+    
+    let started = Instant::now();
+    let mut connection = DedicatedConnection::connect(connection_config).await?;
+    
+    let mut results = Vec::new();
+    let mut failed = false;
+    if transactional {
+        let transaction = connection.client.transaction().await?;
+        for (index, statement) in statements.iter().enumerate() {
+            let result = run_execute_statement(&transaction, &mut connection.messages, &statement.sql, &sql_params, expect_rows)
+                .await
+                .map_err(|e| anyhow::anyhow!("Statement {} (line {}) failed, transaction rolled back: {}", index + 1, statement.line, e))?;
+            results.push(result);
+        }
+        transaction.commit().await?;
+    } else {
+        for statement in &statements {
+            match run_execute_statement(&connection.client, &mut connection.messages, &statement.sql, &sql_params, expect_rows).await {
+                Ok(result) => results.push(result),
+                Err(e) => {
+                    results.push(json!({
+                        "command": statement.sql.split_whitespace().next().unwrap_or_default().to_uppercase(),
+                        "line": statement.line,
+                        "error": execute_error_to_json(&e),
+                        "notices": drain_notices(&mut connection.messages),
+                    }));
+                    failed = true;
+                    break;
+                },
+            }
+        }
+    }
+    
+    let result_json = json!({
+        "statements": statements.len(),
+        "executed": results.len() - usize::from(failed),
+        "transactional": transactional,
+        "duration_ms": started.elapsed().as_secs_f64() * 1000.0,
+        "results": results,
+    });
+    println!("{}", format_output(&result_json, options.format));
+    
+    if failed {
+        let line = statements[results.len() - 1].line;
+        return Err(anyhow::anyhow!("Statement {} (line {}) failed, earlier statements were committed", results.len(), line));
+    }
+    
+    Ok(())
+}
+
+/// One statement of a SQL script and the line it starts on
+#[derive(Debug, PartialEq)]
+struct ScriptStatement {
+    line: usize,
+    sql: String,
+}
+
+/// Split a SQL script on top-level semicolons. Comments, quoted strings and
+/// identifiers, dollar-quoted bodies, parentheses and `BEGIN ATOMIC ... END`
+/// function bodies are skipped over, so semicolons inside them don't end a
+/// statement
+fn split_sql_script(script: &str) -> Vec<ScriptStatement> {
+    let bytes = script.as_bytes();
+    let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_' || b >= 0x80;
+    
+    let mut statements = Vec::new();
+    let mut push_statement = |start: usize, end: usize| {
+        let sql = script[start..end].trim_end();
+        if !sql.is_empty() {
+            let line = script[..start].matches('\n').count() + 1;
+            statements.push(ScriptStatement { line, sql: sql.to_string() });
+        }
+    };
+    
+    // Offset of the first byte of the current statement that isn't
+    // whitespace or a leading comment
+    let mut start: Option<usize> = None;
+    let mut paren_depth = 0usize;
+    // Open BEGIN ATOMIC bodies and the CASE expressions inside them, each closed by END
+    let mut block_depth = 0usize;
+    let mut last_word = String::new();
+    let mut i = 0;
+    
+    while i < bytes.len() {
+        let next = bytes.get(i + 1).copied();
+        match bytes[i] {
+            b'-' if next == Some(b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                continue;
+            },
+            b'/' if next == Some(b'*') => {
+                // Block comments nest in PostgreSQL
+                let mut depth = 0;
+                while i < bytes.len() {
+                    if bytes[i..].starts_with(b"/*") {
+                        depth += 1;
+                        i += 2;
+                    } else if bytes[i..].starts_with(b"*/") {
+                        depth -= 1;
+                        i += 2;
+                        if depth == 0 {
+                            break;
+                        }
+                    } else {
+                        i += 1;
+                    }
+                }
+                continue;
+            },
+            b if b.is_ascii_whitespace() && start.is_none() => {
+                i += 1;
+                continue;
+            },
+            _ => {},
+        }
+        
+        let statement_start = *start.get_or_insert(i);
+        match bytes[i] {
+            b'\'' => {
+                // E'...' strings also allow backslash escapes
+                let escapes = i > 0 && matches!(bytes[i - 1], b'E' | b'e') && (i < 2 || !is_ident(bytes[i - 2]));
+                i += 1;
+                while i < bytes.len() {
+                    match bytes[i] {
+                        b'\\' if escapes => i += 2,
+                        b'\'' if bytes.get(i + 1) == Some(&b'\'') => i += 2,
+                        b'\'' => break,
+                        _ => i += 1,
+                    }
+                }
+                i += 1;
+            },
+            b'"' => {
+                i += 1;
+                while i < bytes.len() {
+                    match bytes[i] {
+                        b'"' if bytes.get(i + 1) == Some(&b'"') => i += 2,
+                        b'"' => break,
+                        _ => i += 1,
+                    }
+                }
+                i += 1;
+            },
+            // $tag$ opens a dollar-quoted string, while $1 is a parameter
+            // and foo$bar an identifier
+            b'$' if (i == 0 || !is_ident(bytes[i - 1])) && !next.is_some_and(|b| b.is_ascii_digit()) => {
+                let tag_end = bytes[i + 1..].iter().position(|&b| !is_ident(b)).map(|offset| i + 1 + offset);
+                match tag_end {
+                    Some(end) if bytes[end] == b'$' => {
+                        let tag = &script[i..=end];
+                        i = match script[end + 1..].find(tag) {
+                            Some(close) => end + 1 + close + tag.len(),
+                            None => bytes.len(),
+                        };
+                    },
+                    _ => i += 1,
+                }
+            },
+            b'(' => {
+                paren_depth += 1;
+                i += 1;
+            },
+            b')' => {
+                paren_depth = paren_depth.saturating_sub(1);
+                i += 1;
+            },
+            b';' if paren_depth == 0 && block_depth == 0 => {
+                push_statement(statement_start, i);
+                start = None;
+                i += 1;
+            },
+            b if is_ident(b) => {
+                let word_end = bytes[i..].iter().position(|&b| !is_ident(b)).map_or(bytes.len(), |offset| i + offset);
+                let word = script[i..word_end].to_ascii_uppercase();
+                match word.as_str() {
+                    "ATOMIC" if last_word == "BEGIN" => block_depth += 1,
+                    "CASE" if block_depth > 0 => block_depth += 1,
+                    "END" if block_depth > 0 => block_depth -= 1,
+                    _ => {},
+                }
+                last_word = word;
+                i = word_end;
+            },
+            _ => i += 1,
+        }
+    }
+    
+    if let Some(statement_start) = start {
+        push_statement(statement_start, bytes.len());
+    }
+    
+    statements
+}
+
+/// Whether a statement would end or replace the script's own transaction
+fn is_transaction_control(sql: &str) -> bool {
+    let mut words = sql.split_whitespace().map(|word| word.trim_end_matches(';').to_uppercase());
+    match words.next().as_deref() {
+        Some("BEGIN" | "START" | "COMMIT" | "END" | "ABORT") => true,
+        Some("ROLLBACK") => words.next().as_deref() != Some("TO"),
+        _ => false,
+    }
+}
+
+/// Structured form of a server error or notice
+fn db_error_to_json(db_error: &tokio_postgres::error::DbError) -> Value {
+    json!({
+        "severity": db_error.severity(),
+        "message": db_error.message(),
+        "code": db_error.code().code(),
+        "detail": db_error.detail(),
+        "hint": db_error.hint(),
+        "position": match db_error.position() {
+            Some(tokio_postgres::error::ErrorPosition::Original(position)) => Some(*position),
+            _ => None,
+        },
+    })
+}
+
+/// Structured form of a statement error, with the server's details when available
+fn statement_error_to_json(error: &tokio_postgres::Error) -> Value {
+    match error.as_db_error() {
+        Some(db_error) => db_error_to_json(db_error),
+        None => json!({ "message": error.to_string() }),
+    }
+}
+
+async fn handle_script_command(
+    pool: &Pool,
+    file: String,
+    savepoints: bool,
+    continue_on_error: bool,
+    dry_run: bool,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let script = fs::read_to_string(&file)
+        .map_err(|e| anyhow::anyhow!("Failed to read script '{}': {}", file, e))?;
+    let statements = split_sql_script(&script);
+    
+    if let Some(statement) = statements.iter().find(|statement| is_transaction_control(&statement.sql)) {
+        return Err(anyhow::anyhow!(
+            "Line {}: transaction control statements are not allowed, the script already runs in one transaction",
+            statement.line
+        ));
+    }
+    
+    let started = Instant::now();
+    let mut client = pool.get().await?;
+    let transaction = client.transaction().await?;
+    
+    let mut results = Vec::new();
+    let (mut succeeded, mut failed) = (0, 0);
+    for (index, statement) in statements.iter().enumerate() {
+        let mut result = json!({
+            "index": index + 1,
+            "line": statement.line,
+            "sql": statement.sql,
+        });
+        
+        // Without savepoints the first error aborts the transaction
+        if failed > 0 && !savepoints {
+            result["status"] = json!("skipped");
+            results.push(result);
+            continue;
+        }
+        
+        if savepoints {
+            transaction.batch_execute("SAVEPOINT _code_tools_script").await?;
+        }
+        
+        let statement_started = Instant::now();
+        match transaction.simple_query(&statement.sql).await {
+            Ok(messages) => {
+                if savepoints {
+                    transaction.batch_execute("RELEASE SAVEPOINT _code_tools_script").await?;
+                }
+                succeeded += 1;
+                
+                let mut rows = Vec::new();
+                let mut affected = 0;
+                let mut returns_rows = false;
+                for message in messages {
+                    match message {
+                        tokio_postgres::SimpleQueryMessage::Row(row) => {
+                            let mut obj = Map::new();
+                            for (i, column) in row.columns().iter().enumerate() {
+                                obj.insert(column.name().to_string(), json!(row.get(i)));
+                            }
+                            rows.push(Value::Object(obj));
+                        },
+                        tokio_postgres::SimpleQueryMessage::RowDescription(_) => returns_rows = true,
+                        tokio_postgres::SimpleQueryMessage::CommandComplete(count) => affected = count,
+                        _ => {},
+                    }
+                }
+                
+                result["status"] = json!("ok");
+                result["rows_affected"] = json!(affected);
+                if returns_rows {
+                    result["rows"] = Value::Array(rows);
+                }
+            },
+            Err(e) => {
+                if savepoints {
+                    transaction.batch_execute("ROLLBACK TO SAVEPOINT _code_tools_script").await?;
+                }
+                failed += 1;
+                
+                result["status"] = json!("error");
+                result["error"] = statement_error_to_json(&e);
+            },
+        }
+        result["duration_ms"] = json!(statement_started.elapsed().as_secs_f64() * 1000.0);
+        results.push(result);
+    }
+    
+    // Failed statements were undone individually, so what succeeded can commit when asked to
+    let commit = !dry_run && (failed == 0 || continue_on_error);
+    if commit {
+        transaction.commit().await?;
+    } else {
+        transaction.rollback().await?;
+    }
+    
+    let result_json = json!({
+        "file": file,
+        "statements": statements.len(),
+        "succeeded": succeeded,
+        "failed": failed,
+        "skipped": statements.len() - succeeded - failed,
+        "committed": commit,
+        "dry_run": dry_run,
+        "savepoints": savepoints,
+        "continue_on_error": continue_on_error,
+        "duration_ms": started.elapsed().as_secs_f64() * 1000.0,
+        "results": results,
+    });
+    println!("{}", format_output(&result_json, options.format));
+    
+    if failed > 0 {
+        return Err(anyhow::anyhow!("{} statement(s) failed", failed));
+    }
+    
+    Ok(())
 }
 
 async fn handle_schema_tables(
     pool: &Pool,
-    table: Option<String>,
+    table: Option<String>,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let query = if let Some(table_name) = table {
+        format!(
+            "SELECT 
+                table_name,
+                table_schema,
+                table_type
+             FROM information_schema.tables 
+             WHERE table_schema NOT IN ('information_schema', 'pg_catalog')
+             AND table_name = '{}'
+             ORDER BY table_schema, table_name",
+            table_name
+        )
+    } else {
+        "SELECT 
+            table_name,
+            table_schema,
+            table_type
+         FROM information_schema.tables 
+         WHERE table_schema NOT IN ('information_schema', 'pg_catalog')
+         ORDER BY table_schema, table_name".to_string()
+    };
+    
+    let client = pool.get().await?;
+    let rows = client.query(&query, &[]).await?;
+    
+    let mut results = Vec::new();
+    for row in rows {
+        results.push(row_to_json(&row)?);
+    }
+    
+    let result_json = Value::Array(results);
+    println!("{}", format_output(&result_json, options.format));
+    
+    Ok(())
+}
+
+/// Filter shared by the schema snapshot queries: user schemas, optionally just one
+const SNAPSHOT_SCHEMA_FILTER: &str = "c.relkind IN ('r', 'p')
+    AND n.nspname NOT IN ('pg_catalog', 'information_schema')
+    AND n.nspname NOT LIKE 'pg_toast%'
+    AND n.nspname NOT LIKE 'pg_temp%'
+    AND ($1::text IS NULL OR n.nspname = $1)";
+
+/// Capture tables, columns, indexes and constraints as a comparable JSON document
+async fn capture_schema_snapshot(
+    client: &deadpool_postgres::Client,
+    schema: Option<&str>,
+) -> Result<Value, anyhow::Error> {
+    let mut tables = Map::new();
+    
+    let table_rows = client.query(&format!(
+        "SELECT n.nspname::text, c.relname::text
+         FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace
+         WHERE {}
+         ORDER BY 1, 2",
+        SNAPSHOT_SCHEMA_FILTER
+    ), &[&schema]).await?;
+    for row in table_rows {
+        let (schema_name, table_name): (String, String) = (row.get(0), row.get(1));
+        tables.insert(format!("{}.{}", schema_name, table_name), json!({
+            "schema": schema_name,
+            "name": table_name,
+            "columns": {},
+            "indexes": {},
+            "constraints": {},
+        }));
+    }
+    
+    let column_rows = client.query(&format!(
+        "SELECT n.nspname || '.' || c.relname,
+                a.attname::text,
+                a.attnum::int4,
+                format_type(a.atttypid, a.atttypmod),
+                NOT a.attnotnull,
+                CASE WHEN a.attgenerated = '' THEN pg_get_expr(d.adbin, d.adrelid) END,
+                CASE WHEN a.attgenerated = 's' THEN pg_get_expr(d.adbin, d.adrelid) END,
+                CASE a.attidentity WHEN 'a' THEN 'always' WHEN 'd' THEN 'by default' END
+         FROM pg_attribute a
+         JOIN pg_class c ON c.oid = a.attrelid
+         JOIN pg_namespace n ON n.oid = c.relnamespace
+         LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum
+         WHERE {} AND a.attnum > 0 AND NOT a.attisdropped",
+        SNAPSHOT_SCHEMA_FILTER
+    ), &[&schema]).await?;
+    for row in column_rows {
+        let key: String = row.get(0);
+        if let Some(table) = tables.get_mut(&key) {
+            table["columns"][row.get::<_, String>(1)] = json!({
+                "position": row.get::<_, i32>(2),
+                "type": row.get::<_, String>(3),
+                "nullable": row.get::<_, bool>(4),
+                "default": row.get::<_, Option<String>>(5),
+                "generated": row.get::<_, Option<String>>(6),
+                "identity": row.get::<_, Option<String>>(7),
+            });
+        }
+    }
+    
+    // Indexes that back a primary key, unique or exclusion constraint are
+    // reported with the constraint instead
+    let index_rows = client.query(&format!(
+        "SELECT n.nspname || '.' || c.relname, i.relname::text, pg_get_indexdef(x.indexrelid)
+         FROM pg_index x
+         JOIN pg_class i ON i.oid = x.indexrelid
+         JOIN pg_class c ON c.oid = x.indrelid
+         JOIN pg_namespace n ON n.oid = c.relnamespace
+         WHERE {}
+           AND NOT EXISTS (
+               SELECT 1 FROM pg_constraint k
+               WHERE k.conrelid = x.indrelid AND k.conindid = x.indexrelid AND k.contype IN ('p', 'u', 'x')
+           )",
+        SNAPSHOT_SCHEMA_FILTER
+    ), &[&schema]).await?;
+    for row in index_rows {
+        let key: String = row.get(0);
+        if let Some(table) = tables.get_mut(&key) {
+            table["indexes"][row.get::<_, String>(1)] = json!({ "definition": row.get::<_, String>(2) });
+        }
+    }
+    
+    let constraint_rows = client.query(&format!(
+        "SELECT n.nspname || '.' || c.relname,
+                k.conname::text,
+                CASE k.contype
+                    WHEN 'p' THEN 'primary_key' WHEN 'u' THEN 'unique' WHEN 'f' THEN 'foreign_key'
+                    WHEN 'c' THEN 'check' WHEN 'x' THEN 'exclusion'
+                END,
+                pg_get_constraintdef(k.oid)
+         FROM pg_constraint k
+         JOIN pg_class c ON c.oid = k.conrelid
+         JOIN pg_namespace n ON n.oid = c.relnamespace
+         WHERE {} AND k.contype IN ('p', 'u', 'f', 'c', 'x')",
+        SNAPSHOT_SCHEMA_FILTER
+    ), &[&schema]).await?;
+    for row in constraint_rows {
+        let key: String = row.get(0);
+        if let Some(table) = tables.get_mut(&key) {
+            table["constraints"][row.get::<_, String>(1)] = json!({
+                "type": row.get::<_, String>(2),
+                "definition": row.get::<_, String>(3),
+            });
+        }
+    }
+    
+    let database: String = client.query_one("SELECT current_database()::text", &[]).await?.get(0);
+    
+    Ok(json!({
+        "database": database,
+        "schema": schema,
+        "captured_at": chrono::Utc::now().to_rfc3339(),
+        "tables": tables,
+    }))
+}
+
+/// Differences between two schema snapshots plus the DDL that would make
+/// the current database match the reference
+struct SchemaDiff {
+    report: Value,
+    ddl: Vec<String>,
+}
+
+/// Objects of one kind split into those only in the current database, only
+/// in the reference, and in both but defined differently
+fn diff_named_objects<'a>(
+    current: &'a Map<String, Value>,
+    reference: &'a Map<String, Value>,
+) -> (Vec<&'a str>, Vec<&'a str>, Vec<&'a str>) {
+    let comparable = |value: &Value| {
+        let mut value = value.clone();
+        if let Value::Object(obj) = &mut value {
+            obj.remove("position");
+        }
+        value
+    };
+    
+    let added = current.keys().filter(|name| !reference.contains_key(*name)).map(String::as_str).collect();
+    let removed = reference.keys().filter(|name| !current.contains_key(*name)).map(String::as_str).collect();
+    let changed = current.iter()
+        .filter(|(name, value)| reference.get(*name).is_some_and(|other| comparable(other) != comparable(value)))
+        .map(|(name, _)| name.as_str())
+        .collect();
+    (added, removed, changed)
+}
+
+/// Column clause for CREATE TABLE / ADD COLUMN from a snapshot column entry
+fn snapshot_column_definition(name: &str, column: &Value) -> String {
+    let column_type = column["type"].as_str().unwrap_or("text");
+    let default = column["default"].as_str();
+    
+    // A sequence default can't be recreated before its sequence exists, so
+    // write it the way it was most likely declared
+    let serial_type = match column_type {
+        "smallint" => Some("smallserial"),
+        "integer" => Some("serial"),
+        "bigint" => Some("bigserial"),
+        _ => None,
+    }.filter(|_| default.is_some_and(|default| default.starts_with("nextval(")));
+    
+    let mut sql = format!("{} {}", quote_ident(name), serial_type.unwrap_or(column_type));
+    if let Some(expression) = column["generated"].as_str() {
+        sql.push_str(&format!(" GENERATED ALWAYS AS ({}) STORED", expression));
+    } else if let Some(default) = default.filter(|_| serial_type.is_none()) {
+        sql.push_str(&format!(" DEFAULT {}", default));
+    }
+    if let Some(identity) = column["identity"].as_str() {
+        sql.push_str(&format!(" GENERATED {} AS IDENTITY", identity.to_uppercase()));
+    }
+    if column["nullable"] == Value::Bool(false) {
+        sql.push_str(" NOT NULL");
+    }
+    sql
+}
+
+/// Compare the current schema snapshot against a reference snapshot
+fn diff_schema_snapshots(current: &Value, reference: &Value) -> SchemaDiff {
+    let empty = Map::new();
+    let tables_of = |snapshot: &'_ Value| snapshot["tables"].as_object().cloned().unwrap_or_default();
+    let current_tables = tables_of(current);
+    let reference_tables = tables_of(reference);
+    let qualified = |table: &Value| format!(
+        "{}.{}",
+        quote_ident(table["schema"].as_str().unwrap_or("public")),
+        quote_ident(table["name"].as_str().unwrap_or_default()),
+    );
+    
+    let mut report = json!({
+        "tables": { "added": [], "removed": [] },
+        "columns": { "added": [], "removed": [], "changed": [] },
+        "indexes": { "added": [], "removed": [], "changed": [] },
+        "constraints": { "added": [], "removed": [], "changed": [] },
+    });
+    let mut push = |kind: &str, change: &str, entry: Value| {
+        if let Some(list) = report[kind][change].as_array_mut() {
+            list.push(entry);
+        }
+    };
+    
+    // DDL is collected in phases so dependent objects are dropped before and
+    // created after the tables and columns they rely on
+    let mut drop_constraints = Vec::new();
+    let mut drop_indexes = Vec::new();
+    let mut drop_tables = Vec::new();
+    let mut create_tables = Vec::new();
+    let mut alter_columns = Vec::new();
+    let mut add_constraints = Vec::new();
+    let mut add_foreign_keys = Vec::new();
+    let mut create_indexes = Vec::new();
+    
+    let mut add_constraint = |table_sql: &str, name: &str, constraint: &Value| {
+        let sql = format!(
+            "ALTER TABLE {} ADD CONSTRAINT {} {};",
+            table_sql, quote_ident(name), constraint["definition"].as_str().unwrap_or_default()
+        );
+        if constraint["type"] == "foreign_key" {
+            add_foreign_keys.push(sql);
+        } else {
+            add_constraints.push(sql);
+        }
+    };
+    
+    let (added_tables, removed_tables, _) = diff_named_objects(&current_tables, &reference_tables);
+    for key in added_tables {
+        push("tables", "added", json!(key));
+        drop_tables.push(format!("DROP TABLE {};", qualified(&current_tables[key])));
+    }
+    for key in removed_tables {
+        push("tables", "removed", json!(key));
+        let table = &reference_tables[key];
+        let table_sql = qualified(table);
+        
+        let mut columns: Vec<(&String, &Value)> = table["columns"].as_object().unwrap_or(&empty).iter().collect();
+        columns.sort_by_key(|(_, column)| column["position"].as_i64().unwrap_or(0));
+        let column_sql: Vec<String> = columns.iter()
+            .map(|(name, column)| format!("    {}", snapshot_column_definition(name, column)))
+            .collect();
+        create_tables.push(format!("CREATE TABLE {} (\n{}\n);", table_sql, column_sql.join(",\n")));
+        
+        for (name, constraint) in table["constraints"].as_object().unwrap_or(&empty) {
+            add_constraint(&table_sql, name, constraint);
+        }
+        for index in table["indexes"].as_object().unwrap_or(&empty).values() {
+            create_indexes.push(format!("{};", index["definition"].as_str().unwrap_or_default()));
+        }
+    }
+    
+    for (key, table) in &current_tables {
+        let Some(reference_table) = reference_tables.get(key) else { continue };
+        let table_sql = qualified(table);
+        let section = |table: &Value, kind: &str| table[kind].as_object().cloned().unwrap_or_default();
+        
+        let (current_columns, reference_columns) = (section(table, "columns"), section(reference_table, "columns"));
+        let (added, removed, changed) = diff_named_objects(&current_columns, &reference_columns);
+        for name in added {
+            push("columns", "added", json!({ "table": key, "column": name, "definition": current_columns[name] }));
+            alter_columns.push(format!("ALTER TABLE {} DROP COLUMN {};", table_sql, quote_ident(name)));
+        }
+        for name in removed {
+            push("columns", "removed", json!({ "table": key, "column": name, "definition": reference_columns[name] }));
+            alter_columns.push(format!(
+                "ALTER TABLE {} ADD COLUMN {};",
+                table_sql, snapshot_column_definition(name, &reference_columns[name])
+            ));
+        }
+        for name in changed {
+            let (current_column, reference_column) = (&current_columns[name], &reference_columns[name]);
+            push("columns", "changed", json!({
+                "table": key,
+                "column": name,
+                "current": current_column,
+                "reference": reference_column,
+            }));
+            
+            let column_sql = format!("ALTER TABLE {} ALTER COLUMN {}", table_sql, quote_ident(name));
+            if current_column["generated"] != reference_column["generated"]
+                || current_column["identity"] != reference_column["identity"] {
+                alter_columns.push(format!(
+                    "-- {}.{}: identity or generation expression differs, reconcile manually",
+                    key, name
+                ));
+            }
+            if current_column["type"] != reference_column["type"] {
+                let column_type = reference_column["type"].as_str().unwrap_or("text");
+                alter_columns.push(format!(
+                    "{} TYPE {} USING {}::{};",
+                    column_sql, column_type, quote_ident(name), column_type
+                ));
+            }
+            if current_column["default"] != reference_column["default"] && reference_column["generated"].is_null() {
+                match reference_column["default"].as_str() {
+                    Some(default) => alter_columns.push(format!("{} SET DEFAULT {};", column_sql, default)),
+                    None => alter_columns.push(format!("{} DROP DEFAULT;", column_sql)),
+                }
+            }
+            if current_column["nullable"] != reference_column["nullable"] {
+                let action = if reference_column["nullable"] == Value::Bool(false) { "SET" } else { "DROP" };
+                alter_columns.push(format!("{} {} NOT NULL;", column_sql, action));
+            }
+        }
+        
+        let (current_constraints, reference_constraints) = (section(table, "constraints"), section(reference_table, "constraints"));
+        let (added, removed, changed) = diff_named_objects(&current_constraints, &reference_constraints);
+        for name in added.iter().chain(changed.iter()) {
+            drop_constraints.push(format!("ALTER TABLE {} DROP CONSTRAINT {};", table_sql, quote_ident(name)));
+        }
+        for name in removed.iter().chain(changed.iter()) {
+            add_constraint(&table_sql, name, &reference_constraints[*name]);
+        }
+        for name in added {
+            push("constraints", "added", json!({ "table": key, "constraint": name, "definition": current_constraints[name] }));
+        }
+        for name in removed {
+            push("constraints", "removed", json!({ "table": key, "constraint": name, "definition": reference_constraints[name] }));
+        }
+        for name in changed {
+            push("constraints", "changed", json!({
+                "table": key,
+                "constraint": name,
+                "current": current_constraints[name],
+                "reference": reference_constraints[name],
+            }));
+        }
+        
+        let (current_indexes, reference_indexes) = (section(table, "indexes"), section(reference_table, "indexes"));
+        let schema_sql = quote_ident(table["schema"].as_str().unwrap_or("public"));
+        let (added, removed, changed) = diff_named_objects(&current_indexes, &reference_indexes);
+        for name in added.iter().chain(changed.iter()) {
+            drop_indexes.push(format!("DROP INDEX {}.{};", schema_sql, quote_ident(name)));
+        }
+        for name in removed.iter().chain(changed.iter()) {
+            create_indexes.push(format!("{};", reference_indexes[*name]["definition"].as_str().unwrap_or_default()));
+        }
+        for name in added {
+            push("indexes", "added", json!({ "table": key, "index": name, "definition": current_indexes[name] }));
+        }
+        for name in removed {
+            push("indexes", "removed", json!({ "table": key, "index": name, "definition": reference_indexes[name] }));
+        }
+        for name in changed {
+            push("indexes", "changed", json!({
+                "table": key,
+                "index": name,
+                "current": current_indexes[name],
+                "reference": reference_indexes[name],
+            }));
+        }
+    }
+    
+    let ddl: Vec<String> = [
+        drop_constraints, drop_indexes, drop_tables, create_tables,
+        alter_columns, add_constraints, add_foreign_keys, create_indexes,
+    ].concat();
+    report["in_sync"] = json!(ddl.is_empty());
+    
+    SchemaDiff { report, ddl }
+}
+
+async fn handle_schema_snapshot(
+    pool: &Pool,
+    output: Option<String>,
+    schema: Option<String>,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let client = pool.get().await?;
+    let snapshot = capture_schema_snapshot(&client, schema.as_deref()).await?;
+    
+    match output {
+        Some(path) => {
+            fs::write(&path, serde_json::to_string_pretty(&snapshot)?)
+                .map_err(|e| anyhow::anyhow!("Failed to write snapshot '{}': {}", path, e))?;
+            let table_count = snapshot["tables"].as_object().map_or(0, Map::len);
+            let result = json!({ "output": path, "tables": table_count });
+            println!("{}", format_output(&result, options.format));
+        },
+        None => println!("{}", format_output(&snapshot, options.format)),
+    }
+    
+    Ok(())
+}
+
+async fn handle_schema_diff(
+    pool: &Pool,
+    against_url: Option<String>,
+    snapshot_path: Option<String>,
+    schema: Option<String>,
+    ddl: bool,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let mut reference = match (against_url, snapshot_path.as_ref()) {
+        (Some(url), _) => {
+            // The reference database is given only as a URL
+            let mut reference_config = Config::new();
+            reference_config.url = Some(url);
+            let reference_pool = create_pool(reference_config).await?;
+            let reference_client = reference_pool.get().await
+                .map_err(|e| anyhow::anyhow!("Failed to connect to reference database: {}", e))?;
+            capture_schema_snapshot(&reference_client, schema.as_deref()).await?
+        },
+        (None, Some(path)) => {
+            let content = fs::read_to_string(path)
+                .map_err(|e| anyhow::anyhow!("Failed to read snapshot '{}': {}", path, e))?;
+            parse_json_arg(&content, "snapshot")?
+        },
+        (None, None) => return Err(anyhow::anyhow!("Specify --against-url or --snapshot to compare with")),
+    };
+    
+    // A snapshot saved for one schema is compared against that schema only
+    let schema = schema.or_else(|| reference["schema"].as_str().map(str::to_string));
+    if let (Some(schema), Some(tables)) = (&schema, reference["tables"].as_object_mut()) {
+        tables.retain(|_, table| table["schema"].as_str() == Some(schema.as_str()));
+    }
+    
+    let client = pool.get().await?;
+    let current = capture_schema_snapshot(&client, schema.as_deref()).await?;
+    
+    let diff = diff_schema_snapshots(&current, &reference);
+    let mut result = diff.report;
+    result["current"] = current["database"].clone();
+    result["reference"] = json!({
+        "database": reference["database"],
+        "captured_at": reference["captured_at"],
+        "snapshot": snapshot_path,
+    });
+    if ddl {
+        result["ddl"] = json!(diff.ddl);
+    }
+    
+    println!("{}", format_output(&result, options.format));
+    
+    Ok(())
+}
+
+async fn query_to_json(
+    client: &deadpool_postgres::Client,
+    query: &str,
+    params: &[&(dyn ToSql + Sync)],
+) -> Result<Vec<Value>, anyhow::Error> {
+    let rows = client.query(query, params).await?;
+    
+    let mut results = Vec::new();
+    for row in rows {
+        results.push(row_to_json(&row)?);
+    }
+    
+    Ok(results)
+}
+
+async fn handle_monitor_stats(
+    pool: &Pool,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let query = "SELECT
+            datname::text AS database,
+            numbackends AS connections,
+            xact_commit,
+            xact_rollback,
+            blks_read,
+            blks_hit,
+            round(blks_hit * 100.0 / nullif(blks_hit + blks_read, 0), 2)::float8 AS cache_hit_ratio,
+            tup_returned,
+            tup_fetched,
+            tup_inserted,
+            tup_updated,
+            tup_deleted,
+            conflicts,
+            temp_files,
+            temp_bytes,
+            deadlocks,
+            pg_database_size(datname) AS size_bytes,
+            pg_size_pretty(pg_database_size(datname)) AS size,
+            stats_reset
+         FROM pg_stat_database
+         WHERE datname = current_database()";
+    
+    let client = pool.get().await?;
+    let results = query_to_json(&client, query, &[]).await?;
+    
+    let result_json = Value::Array(results);
+    println!("{}", format_output(&result_json, options.format));
+    
+    Ok(())
+}
+
+async fn handle_monitor_connections(
+    pool: &Pool,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let query = "SELECT
+            pid,
+            usename::text AS username,
+            application_name,
+            host(client_addr) AS client_addr,
+            state,
+            wait_event_type,
+            wait_event,
+            backend_start,
+            xact_start,
+            query_start,
+            (EXTRACT(EPOCH FROM (now() - query_start)) * 1000)::float8 AS query_duration_ms,
+            query
+         FROM pg_stat_activity
+         WHERE datname = current_database()
+         AND pid <> pg_backend_pid()
+         ORDER BY query_start NULLS LAST";
+    
+    let client = pool.get().await?;
+    let results = query_to_json(&client, query, &[]).await?;
+    
+    let result_json = Value::Array(results);
+    println!("{}", format_output(&result_json, options.format));
+    
+    Ok(())
+}
+
+async fn handle_monitor_slow_queries(
+    pool: &Pool,
+    min_duration: f64,
+    limit: i64,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let client = pool.get().await?;
+    
+    let has_pg_stat_statements: bool = client.query_one(
+        "SELECT EXISTS (SELECT 1 FROM pg_extension WHERE extname = 'pg_stat_statements')",
+        &[],
+    ).await?.get(0);
+    
+    let mut statement_stats = None;
+    if has_pg_stat_statements {
+        // Timing columns were renamed in PostgreSQL 13
+        let server_version: i32 = client.query_one(
+            "SELECT current_setting('server_version_num')::int4",
+            &[],
+        ).await?.get(0);
+        let prefix = if server_version >= 130000 { "exec_time" } else { "time" };
+        
+        let query = format!(
+            "SELECT
+                'pg_stat_statements' AS source,
+                queryid,
+                calls,
+                total_{prefix}::float8 AS total_time_ms,
+                mean_{prefix}::float8 AS mean_time_ms,
+                max_{prefix}::float8 AS max_time_ms,
+                rows,
+                query
+             FROM pg_stat_statements
+             WHERE dbid = (SELECT oid FROM pg_database WHERE datname = current_database())
+             AND mean_{prefix} >= $1
+             ORDER BY mean_{prefix} DESC
+             LIMIT $2",
+            prefix = prefix
+        );
+        match query_to_json(&client, &query, &[&min_duration, &limit]).await {
+            Ok(rows) => statement_stats = Some(rows),
+            // The extension can be created without its library being loaded at server start
+            Err(e) if e.downcast_ref::<tokio_postgres::Error>().and_then(|e| e.code())
+                == Some(&tokio_postgres::error::SqlState::OBJECT_NOT_IN_PREREQUISITE_STATE) => {
+                eprintln!("Warning: pg_stat_statements is installed but not in shared_preload_libraries \
+                    (add it and restart the server); showing running queries from pg_stat_activity instead");
+            },
+            Err(e) => return Err(e),
+        }
+    }
+    
+    let results = if let Some(rows) = statement_stats {
+        rows
+    } else {
+        let query = "SELECT
+                'pg_stat_activity' AS source,
+                pid,
+                usename::text AS username,
+                state,
+                query_start,
+                (EXTRACT(EPOCH FROM (now() - query_start)) * 1000)::float8 AS duration_ms,
+                query
+             FROM pg_stat_activity
+             WHERE datname = current_database()
+             AND state <> 'idle'
+             AND pid <> pg_backend_pid()
+             AND (EXTRACT(EPOCH FROM (now() - query_start)) * 1000)::float8 >= $1
+             ORDER BY query_start
+             LIMIT $2";
+        query_to_json(&client, query, &[&min_duration, &limit]).await?
+    };
+    
+    let result_json = Value::Array(results);
+    println!("{}", format_output(&result_json, options.format));
+    
+    Ok(())
+}
+
+/// Privileges accepted for each object kind in a permissions document
+const DATABASE_PRIVILEGES: &[&str] = &["CONNECT", "CREATE", "TEMPORARY", "TEMP", "ALL"];
+const SCHEMA_PRIVILEGES: &[&str] = &["USAGE", "CREATE", "ALL"];
+const TABLE_PRIVILEGES: &[&str] = &["SELECT", "INSERT", "UPDATE", "DELETE", "TRUNCATE", "REFERENCES", "TRIGGER", "ALL"];
+const SEQUENCE_PRIVILEGES: &[&str] = &["USAGE", "SELECT", "UPDATE", "ALL"];
+
+fn parse_privileges(value: &Value, allowed: &[&str], target: &str) -> Result<String, anyhow::Error> {
+    let list = match value {
+        Value::String(s) => vec![s.clone()],
+        Value::Array(arr) => arr.iter()
+            .map(|v| v.as_str()
+                .map(str::to_string)
+                .ok_or_else(|| anyhow::anyhow!("Privileges for '{}' must be strings", target)))
+            .collect::<Result<Vec<_>, _>>()?,
+        _ => return Err(anyhow::anyhow!("Privileges for '{}' must be a string or array", target)),
+    };
+    
+    if list.is_empty() {
+        return Err(anyhow::anyhow!("No privileges given for '{}'", target));
+    }
+    
+    let mut privileges = Vec::new();
+    for privilege in list {
+        let upper = privilege.trim().to_uppercase();
+        let normalized = if upper == "ALL PRIVILEGES" { "ALL".to_string() } else { upper };
+        if !allowed.contains(&normalized.as_str()) {
+            return Err(anyhow::anyhow!("Invalid privilege '{}' for '{}' (allowed: {})",
+                privilege, target, allowed.join(", ")));
+        }
+        privileges.push(normalized);
+    }
+    
+    Ok(privileges.join(", "))
+}
+
+/// Build GRANT (or REVOKE) statements for a role from a permissions document:
+///
+/// {"database": [...], "schemas": {"name": [...]}, "tables": {"schema.table" | "schema.*": [...]},
+///  "sequences": {"schema.seq" | "schema.*": [...]}, "roles": ["role", ...]}
+///
+/// Names containing dots are double-quoted, e.g. `"my.schema"."my.table"`
+fn build_privilege_statements(
+    role: &str,
+    permissions: &Value,
+    database: &str,
+    grant: bool,
+) -> Result<Vec<String>, anyhow::Error> {
+    let perms = permissions.as_object()
+        .ok_or_else(|| anyhow::anyhow!("Permissions must be a JSON object"))?;
+    
+    let role_ident = quote_ident(role);
+    let (verb, preposition) = if grant { ("GRANT", "TO") } else { ("REVOKE", "FROM") };
+    let mut statements = Vec::new();
+    
+    for (kind, spec) in perms {
+        match kind.as_str() {
+            "database" => {
+                let privileges = parse_privileges(spec, DATABASE_PRIVILEGES, "database")?;
+                statements.push(format!("{} {} ON DATABASE {} {} {}",
+                    verb, privileges, quote_ident(database), preposition, role_ident));
+            },
+            "schemas" | "tables" | "sequences" => {
+                let targets = spec.as_object()
+                    .ok_or_else(|| anyhow::anyhow!("'{}' must map object names to privileges", kind))?;
+                
+                for (target, privs) in targets {
+                    let (allowed, object_kind, all_kind) = match kind.as_str() {
+                        "schemas" => (SCHEMA_PRIVILEGES, "SCHEMA", None),
+                        "tables" => (TABLE_PRIVILEGES, "TABLE", Some("ALL TABLES")),
+                        _ => (SEQUENCE_PRIVILEGES, "SEQUENCE", Some("ALL SEQUENCES")),
+                    };
+                    let privileges = parse_privileges(privs, allowed, target)?;
+                    
+                    let object = match (target.strip_suffix(".*"), all_kind) {
+                        (Some(schema), Some(all_kind)) => {
+                            format!("{} IN SCHEMA {}", all_kind, quote_qualified_ident(schema))
+                        },
+                        _ => format!("{} {}", object_kind, quote_qualified_ident(target)),
+                    };
+                    statements.push(format!("{} {} ON {} {} {}",
+                        verb, privileges, object, preposition, role_ident));
+                }
+            },
+            "roles" => {
+                let roles = spec.as_array()
+                    .ok_or_else(|| anyhow::anyhow!("'roles' must be an array of role names"))?;
+                
+                for member_of in roles {
+                    let name = member_of.as_str()
+                        .ok_or_else(|| anyhow::anyhow!("Role names must be strings"))?;
+                    statements.push(format!("{} {} {} {}",
+                        verb, quote_ident(name), preposition, role_ident));
+                }
+            },
+            other => return Err(anyhow::anyhow!(
+                "Unknown permission kind '{}' (expected database, schemas, tables, sequences or roles)", other)),
+        }
+    }
+    
+    Ok(statements)
+}
+
+/// Build a CREATE ROLE statement from the user options document
+fn build_create_role_statement(
+    username: &str,
+    password: &str,
+    options: &Map<String, Value>,
+) -> Result<String, anyhow::Error> {
+    let mut clauses = Vec::new();
+    
+    // Users can log in unless explicitly disabled
+    if !options.contains_key("login") {
+        clauses.push("LOGIN".to_string());
+    }
+    
+    for (key, value) in options {
+        let flag = |keyword: &str| -> Result<String, anyhow::Error> {
+            let enabled = value.as_bool()
+                .ok_or_else(|| anyhow::anyhow!("Option '{}' must be a boolean", key))?;
+            Ok(if enabled { keyword.to_string() } else { format!("NO{}", keyword) })
+        };
+        
+        match key.as_str() {
+            "login" => clauses.push(flag("LOGIN")?),
+            "superuser" => clauses.push(flag("SUPERUSER")?),
+            "createdb" => clauses.push(flag("CREATEDB")?),
+            "createrole" => clauses.push(flag("CREATEROLE")?),
+            "inherit" => clauses.push(flag("INHERIT")?),
+            "replication" => clauses.push(flag("REPLICATION")?),
+            "bypassrls" => clauses.push(flag("BYPASSRLS")?),
+            "connection_limit" => {
+                let limit = value.as_i64()
+                    .ok_or_else(|| anyhow::anyhow!("Option 'connection_limit' must be an integer"))?;
+                clauses.push(format!("CONNECTION LIMIT {}", limit));
+            },
+            "valid_until" => {
+                let until = value.as_str()
+                    .ok_or_else(|| anyhow::anyhow!("Option 'valid_until' must be a timestamp string"))?;
+                clauses.push(format!("VALID UNTIL {}", quote_literal(until)));
+            },
+            "in_roles" => {
+                let roles = value.as_array()
+                    .ok_or_else(|| anyhow::anyhow!("Option 'in_roles' must be an array of role names"))?
+                    .iter()
+                    .map(|r| r.as_str()
+                        .map(quote_ident)
+                        .ok_or_else(|| anyhow::anyhow!("Role names must be strings")))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if !roles.is_empty() {
+                    clauses.push(format!("IN ROLE {}", roles.join(", ")));
+                }
+            },
+            "permissions" => {},
+            other => return Err(anyhow::anyhow!("Unknown user option '{}'", other)),
+        }
+    }
+    
+    clauses.push(format!("PASSWORD {}", quote_literal(password)));
+    
+    Ok(format!("CREATE ROLE {} WITH {}", quote_ident(username), clauses.join(" ")))
+}
+
+async fn handle_users_list(
+    pool: &Pool,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let query = "SELECT
+            r.rolname::text AS username,
+            r.rolcanlogin AS can_login,
+            r.rolsuper AS superuser,
+            r.rolcreatedb AS createdb,
+            r.rolcreaterole AS createrole,
+            r.rolinherit AS inherit,
+            r.rolreplication AS replication,
+            r.rolbypassrls AS bypassrls,
+            r.rolconnlimit AS connection_limit,
+            r.rolvaliduntil AS valid_until,
+            to_json(ARRAY(
+                SELECT b.rolname::text
+                FROM pg_auth_members m
+                JOIN pg_roles b ON m.roleid = b.oid
+                WHERE m.member = r.oid
+                ORDER BY 1
+            )) AS member_of
+         FROM pg_roles r
+         WHERE r.rolname !~ '^pg_'
+         ORDER BY r.rolname";
+    
+    let client = pool.get().await?;
+    let results = query_to_json(&client, query, &[]).await?;
+    
+    let result_json = Value::Array(results);
+    println!("{}", format_output(&result_json, options.format));
+    
+    Ok(())
+}
+
+async fn handle_users_create(
+    pool: &Pool,
+    username: String,
+    password: String,
+    options_str: String,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let user_options = match parse_json_arg(&options_str, "options")? {
+        Value::Object(obj) => obj,
+        _ => return Err(anyhow::anyhow!("User options must be a JSON object")),
+    };
+    
+    let create_statement = build_create_role_statement(&username, &password, &user_options)?;
+    
+    let mut client = pool.get().await?;
+    let database: String = client.query_one("SELECT current_database()::text", &[]).await?.get(0);
+    
+    let grants = match user_options.get("permissions") {
+        Some(permissions) => build_privilege_statements(&username, permissions, &database, true)?,
+        None => Vec::new(),
+    };
+    
+    let transaction = client.transaction().await?;
+    transaction.batch_execute(&create_statement).await?;
+    for statement in &grants {
+        transaction.batch_execute(statement).await?;
+    }
+    transaction.commit().await?;
+    
+    let result = json!({
+        "username": username,
+        "created": true,
+        "grants": grants
+    });
+    println!("{}", format_output(&result, options.format));
+    
+    Ok(())
+}
+
+async fn handle_users_privileges(
+    pool: &Pool,
+    username: String,
+    permissions_str: String,
+    grant: bool,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let permissions = parse_json_arg(&permissions_str, "permissions")?;
+    
+    let mut client = pool.get().await?;
+    let database: String = client.query_one("SELECT current_database()::text", &[]).await?.get(0);
+    let statements = build_privilege_statements(&username, &permissions, &database, grant)?;
+    
+    let transaction = client.transaction().await?;
+    for statement in &statements {
+        transaction.batch_execute(statement).await?;
+    }
+    transaction.commit().await?;
+    
+    let result = json!({
+        "username": username,
+        "operation": if grant { "grant" } else { "revoke" },
+        "statements": statements
+    });
+    println!("{}", format_output(&result, options.format));
+    
+    Ok(())
+}
+
+async fn handle_users_drop(
+    pool: &Pool,
+    username: String,
+    reassign_to: Option<String>,
+    if_exists: bool,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let role_ident = quote_ident(&username);
+    let mut statements = Vec::new();
+    
+    if let Some(ref new_owner) = reassign_to {
+        statements.push(format!("REASSIGN OWNED BY {} TO {}", role_ident, quote_ident(new_owner)));
+        statements.push(format!("DROP OWNED BY {}", role_ident));
+    }
+    statements.push(format!("DROP ROLE {}{}", if if_exists { "IF EXISTS " } else { "" }, role_ident));
+    
+    let mut client = pool.get().await?;
+    let transaction = client.transaction().await?;
+    for statement in &statements {
+        transaction.batch_execute(statement).await?;
+    }
+    transaction.commit().await?;
+    
+    let result = json!({
+        "username": username,
+        "dropped": true,
+        "statements": statements
+    });
+    println!("{}", format_output(&result, options.format));
+    
+    Ok(())
+}
+
+/// Size of the buffer flushed to the server during COPY FROM STDIN
+const COPY_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Columns a COPY or INSERT can write to: generated columns and
+/// GENERATED ALWAYS identity columns are left out
+async fn insertable_columns<C: tokio_postgres::GenericClient>(
+    client: &C,
+    table: &str,
+) -> Result<Vec<String>, anyhow::Error> {
+    Ok(client.query(
+        "SELECT attname::text FROM pg_attribute
+         WHERE attrelid = $1::text::regclass AND attnum > 0 AND NOT attisdropped
+           AND attgenerated = '' AND attidentity <> 'a'
+         ORDER BY attnum",
+        &[&table],
+    ).await?
+        .iter()
+        .map(|row| row.get(0))
+        .collect())
+}
+
+/// Split a CSV header line into column names
+fn parse_csv_header(line: &str) -> Result<Vec<String>, anyhow::Error> {
+    let line = line.trim_start_matches('\u{feff}').trim_end_matches(['\r', '\n']);
+    let mut columns = vec![String::new()];
+    let mut chars = line.chars().peekable();
+    let mut in_quotes = false;
+    
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                columns.last_mut().unwrap().push('"');
+            },
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => columns.push(String::new()),
+            _ => columns.last_mut().unwrap().push(c),
+        }
+    }
+    
+    if in_quotes {
+        return Err(anyhow::anyhow!("Unterminated quoted column name in CSV header"));
+    }
+    if columns.iter().any(|column| column.is_empty()) {
+        return Err(anyhow::anyhow!("CSV header has an empty column name"));
+    }
+    
+    Ok(columns)
+}
+
+/// Append a JSON document as a single quoted CSV field line
+fn push_csv_document(buffer: &mut String, doc: &Value) {
+    buffer.push('"');
+    buffer.push_str(&doc.to_string().replace('"', "\"\""));
+    buffer.push_str("\"\n");
+}
+
+async fn handle_transfer_export(
+    pool: &Pool,
+    table: String,
+    output: String,
+    where_clause: Option<String>,
+    limit: Option<i64>,
+    file_format: Option<TransferFormat>,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let format = TransferFormat::resolve(file_format, &output);
+    
+    let client = pool.get().await?;
+    
+    // CSV files are loaded back by column name, so leave out the columns an import can't write
+    let select_list = match format {
+        TransferFormat::Csv => insertable_columns(&**client, &table).await?
+            .iter()
+            .map(|column| quote_ident(column))
+            .collect::<Vec<_>>()
+            .join(", "),
+        _ => "*".to_string(),
+    };
+    
+    let mut source_query = format!("SELECT {} FROM {}", select_list, table);
+    if let Some(ref filter) = where_clause {
+        source_query.push_str(&format!(" WHERE {}", filter));
+    }
+    if let Some(limit_val) = limit {
+        source_query.push_str(&format!(" LIMIT {}", limit_val));
+    }
+    
+    let file = File::create(&output)
+        .map_err(|e| anyhow::anyhow!("Failed to create output file '{}': {}", output, e))?;
+    let mut writer = BufWriter::new(file);
+    let mut rows_exported: u64 = 0;
+    
+    match format {
+        TransferFormat::Csv => {
+            let copy_query = format!(
+                "COPY ({}) TO STDOUT WITH (FORMAT csv, HEADER true)",
+                source_query
+            );
+            let stream = client.copy_out(&copy_query).await?;
+            pin_mut!(stream);
+            
+            // The server sends one CopyData message per row, preceded by the header
+            let mut header_seen = false;
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk?;
+                writer.write_all(&chunk)?;
+                if header_seen {
+                    rows_exported += 1;
+                } else {
+                    header_seen = true;
+                }
+            }
+        },
+        TransferFormat::Jsonl | TransferFormat::Json => {
+            // Serialize rows server-side so every column type round-trips, and
+            // stream them instead of collecting the whole result set
+            let json_query = format!("SELECT row_to_json(t)::text FROM ({}) t", source_query);
+            let no_params: Vec<&(dyn ToSql + Sync)> = Vec::new();
+            let stream = client.query_raw(&json_query, no_params).await?;
+            pin_mut!(stream);
+            
+            if let TransferFormat::Json = format {
+                writer.write_all(b"[")?;
+            }
+            while let Some(row) = stream.next().await {
+                let line: String = row?.get(0);
+                match format {
+                    TransferFormat::Json => {
+                        if rows_exported > 0 {
+                            writer.write_all(b",")?;
+                        }
+                        writer.write_all(b"\n  ")?;
+                        writer.write_all(line.as_bytes())?;
+                    },
+                    _ => {
+                        writer.write_all(line.as_bytes())?;
+                        writer.write_all(b"\n")?;
+                    },
+                }
+                rows_exported += 1;
+            }
+            if let TransferFormat::Json = format {
+                writer.write_all(b"\n]\n")?;
+            }
+        },
+    }
+    
+    writer.flush()?;
+    
+    let result = json!({
+        "table": table,
+        "output": output,
+        "format": format.as_str(),
+        "rows_exported": rows_exported
+    });
+    println!("{}", format_output(&result, options.format));
+    
+    Ok(())
+}
+
+async fn handle_transfer_import(
+    pool: &Pool,
+    table: String,
+    input: String,
+    file_format: Option<TransferFormat>,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let format = TransferFormat::resolve(file_format, &input);
+    
+    let file = File::open(&input)
+        .map_err(|e| anyhow::anyhow!("Failed to open input file '{}': {}", input, e))?;
+    let mut reader = BufReader::new(file);
+    
+    let mut client = pool.get().await?;
+    let transaction = client.transaction().await?;
+    
+    let rows_loaded = match format {
+        TransferFormat::Csv => {
+            // Load by the header's column names rather than the table's column order
+            let mut header = String::new();
+            if reader.read_line(&mut header)? == 0 {
+                return Err(anyhow::anyhow!("CSV file '{}' has no header line", input));
+            }
+            let columns: Vec<String> = parse_csv_header(&header)?
+                .iter()
+                .map(|column| quote_ident(column))
+                .collect();
+            
+            let copy_query = format!("COPY {} ({}) FROM STDIN WITH (FORMAT csv)", table, columns.join(", "));
+            let sink = transaction.copy_in(&copy_query).await?;
+            pin_mut!(sink);
+            
+            let mut buf = vec![0u8; COPY_CHUNK_SIZE];
+            loop {
+                let n = reader.read(&mut buf)?;
+                if n == 0 {
+                    break;
+                }
+                sink.send(Bytes::copy_from_slice(&buf[..n])).await?;
+            }
+            sink.finish().await?
+        },
+        TransferFormat::Jsonl | TransferFormat::Json => {
+            // Stage raw documents in a temp table via COPY, then let the server
+            // map JSON keys onto the target table's columns and types
+            transaction.batch_execute(
+                "CREATE TEMP TABLE _code_tools_import (n bigserial, doc jsonb) ON COMMIT DROP"
+            ).await?;
+            
+            let sink = transaction.copy_in(
+                "COPY _code_tools_import (doc) FROM STDIN WITH (FORMAT csv)"
+            ).await?;
+            pin_mut!(sink);
+            
+            let mut buffer = String::new();
+            
+            if let TransferFormat::Json = format {
+                let data: Value = serde_json::from_reader(reader)
+                    .map_err(|e| anyhow::anyhow!("Failed to parse '{}' as JSON: {}", input, e))?;
+                let records = match data {
+                    Value::Array(arr) => arr,
+                    _ => return Err(anyhow::anyhow!("JSON import file must contain an array of objects")),
+                };
+                for record in &records {
+                    push_csv_document(&mut buffer, record);
+                    if buffer.len() >= COPY_CHUNK_SIZE {
+                        sink.send(Bytes::from(std::mem::take(&mut buffer))).await?;
+                    }
+                }
+            } else {
+                for (line_no, line) in (&mut reader).lines().enumerate() {
+                    let line = line?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let record: Value = serde_json::from_str(&line)
+                        .map_err(|e| anyhow::anyhow!("Invalid JSON on line {}: {}", line_no + 1, e))?;
+                    push_csv_document(&mut buffer, &record);
+                    if buffer.len() >= COPY_CHUNK_SIZE {
+                        sink.send(Bytes::from(std::mem::take(&mut buffer))).await?;
+                    }
+                }
+            }
+            if !buffer.is_empty() {
+                sink.send(Bytes::from(buffer)).await?;
+            }
+            sink.finish().await?;
+            
+            insert_staged_documents(&transaction, &table).await?
+        },
+    };
+    
+    transaction.commit().await?;
+    
+    let result = json!({
+        "table": table,
+        "input": input,
+        "format": format.as_str(),
+        "rows_loaded": rows_loaded
+    });
+    println!("{}", format_output(&result, options.format));
+    
+    Ok(())
+}
+
+/// Check that a raw default expression can't escape the parentheses it is
+/// wrapped in: parentheses must balance outside string literals and quoted
+/// identifiers, and statement separators, comments, dollar quotes and
+/// top-level commas are rejected.
+fn validate_default_expression(expression: &str) -> Result<(), anyhow::Error> {
+    let invalid = |reason: &str| Err(anyhow::anyhow!("Invalid default expression '{}': {}", expression, reason));
+    let chars: Vec<char> = expression.chars().collect();
+    let mut depth = 0usize;
+    let mut i = 0;
+    
+    while i < chars.len() {
+        match chars[i] {
+            '\'' => {
+                // E'...' strings also allow backslash escapes
+                let escapes = i > 0 && matches!(chars[i - 1], 'e' | 'E')
+                    && (i < 2 || !(chars[i - 2].is_alphanumeric() || chars[i - 2] == '_'));
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return invalid("unterminated string literal"),
+                        Some('\\') if escapes => i += 2,
+                        Some('\'') if chars.get(i + 1) == Some(&'\'') => i += 2,
+                        Some('\'') => break,
+                        Some(_) => i += 1,
+                    }
+                }
+            },
+            '"' => {
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return invalid("unterminated quoted identifier"),
+                        Some('"') if chars.get(i + 1) == Some(&'"') => i += 2,
+                        Some('"') => break,
+                        Some(_) => i += 1,
+                    }
+                }
+            },
+            '(' => depth += 1,
+            ')' => {
+                if depth == 0 {
+                    return invalid("unbalanced parentheses");
+                }
+                depth -= 1;
+            },
+            ';' => return invalid("statement separators are not allowed"),
+            '$' => return invalid("dollar quoting is not allowed"),
+            '-' if chars.get(i + 1) == Some(&'-') => return invalid("comments are not allowed"),
+            '/' if chars.get(i + 1) == Some(&'*') => return invalid("comments are not allowed"),
+            ',' if depth == 0 => return invalid("top-level commas are not allowed"),
+            _ => {},
+        }
+        i += 1;
+    }
+    
+    if depth != 0 {
+        return invalid("unbalanced parentheses");
+    }
+    if expression.trim().is_empty() {
+        return invalid("expression is empty");
+    }
+    
+    Ok(())
+}
+
+/// Insert the documents staged in `_code_tools_import` into `table`. Each
+/// distinct set of keys gets its own column list, so keys a document omits
+/// fall back to the column defaults and generated or GENERATED ALWAYS identity
+/// columns are never written.
+async fn insert_staged_documents(
+    transaction: &deadpool_postgres::Transaction<'_>,
+    table: &str,
+) -> Result<u64, anyhow::Error> {
+    let table_columns = insertable_columns(&**transaction, table).await?;
+    
+    let key_sets = transaction.query(
+        "SELECT keys, count(*) FROM (
+             SELECT ARRAY(SELECT jsonb_object_keys(doc) ORDER BY 1) AS keys, n FROM _code_tools_import
+         ) d GROUP BY keys ORDER BY min(n)",
+        &[],
+    ).await?;
+    
+    let mut rows_loaded = 0;
+    for key_set in key_sets {
+        let keys: Vec<String> = key_set.get(0);
+        // Keys that aren't insertable columns are ignored, as jsonb_populate_record does
+        let columns: Vec<&String> = table_columns.iter().filter(|column| keys.contains(column)).collect();
+        
+        if columns.is_empty() {
+            let count: i64 = key_set.get(1);
+            for _ in 0..count {
+                transaction.execute(&format!("INSERT INTO {} DEFAULT VALUES", table), &[]).await?;
+            }
+            rows_loaded += count as u64;
+            continue;
+        }
+        
+        let column_list: Vec<String> = columns.iter().map(|column| quote_ident(column)).collect();
+        let select_list: Vec<String> = column_list.iter().map(|column| format!("r.{}", column)).collect();
+        let insert_query = format!(
+            "INSERT INTO {table} ({columns}) SELECT {values} FROM _code_tools_import i, \
+             jsonb_populate_record(NULL::{table}, i.doc) r \
+             WHERE ARRAY(SELECT jsonb_object_keys(i.doc) ORDER BY 1) = $1 ORDER BY i.n",
+            table = table,
+            columns = column_list.join(", "),
+            values = select_list.join(", "),
+        );
+        rows_loaded += transaction.execute(&insert_query, &[&keys]).await?;
+    }
+    
+    Ok(rows_loaded)
+}
+
+/// Build a CREATE TABLE statement from a JSON column spec. Each column accepts
+/// name, type, nullable, default (a literal value), default_expression (a single
+/// SQL expression such as `now()`), primary_key and unique.
+fn build_create_table_statement(
+    table: &str,
+    columns: &Value,
+    if_not_exists: bool,
+) -> Result<String, anyhow::Error> {
+    let columns = columns.as_array()
+        .ok_or_else(|| anyhow::anyhow!("Column definitions must be a JSON array of objects"))?;
+    
+    if columns.is_empty() {
+        return Err(anyhow::anyhow!("At least one column is required"));
+    }
+    
+    // Type names can't be quoted, so restrict them to a (schema-qualified) name or one of
+    // the multi-word built-in types, with optional modifiers, time zone and array suffixes
+    let type_pattern = regex::Regex::new(
+        r"(?i)^(double precision|character varying|char varying|bit varying|[a-z_][a-z0-9_]*(\.[a-z_][a-z0-9_]*)?)(\(\s*\d+\s*(,\s*\d+\s*)?\))?( with(out)? time zone)?(\[\d*\])*$"
+    ).unwrap();
+    
+    let mut definitions = Vec::new();
+    let mut primary_key = Vec::new();
+    
+    for column in columns {
+        let col = column.as_object()
+            .ok_or_else(|| anyhow::anyhow!("Each column definition must be a JSON object"))?;
+        
+        let name = col.get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow::anyhow!("Column definition is missing 'name'"))?;
+        let col_type = col.get("type")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow::anyhow!("Column '{}' is missing 'type'", name))?
+            .trim();
+        
+        if !type_pattern.is_match(col_type) {
+            return Err(anyhow::anyhow!("Invalid type '{}' for column '{}'", col_type, name));
+        }
+        
+        let mut definition = format!("{} {}", quote_ident(name), col_type);
+        
+        if col.get("nullable").and_then(Value::as_bool) == Some(false) {
+            definition.push_str(" NOT NULL");
+        }
+        
+        match (col.get("default"), col.get("default_expression")) {
+            (Some(_), Some(_)) => {
+                return Err(anyhow::anyhow!("Column '{}' can't have both 'default' and 'default_expression'", name));
+            },
+            (Some(default), None) => {
+                let literal = match default {
+                    Value::String(s) => quote_literal(s),
+                    Value::Number(n) => n.to_string(),
+                    Value::Bool(b) => b.to_string(),
+                    Value::Null => "NULL".to_string(),
+                    _ => quote_literal(&default.to_string()),
+                };
+                definition.push_str(&format!(" DEFAULT {}", literal));
+            },
+            (None, Some(expression)) => {
+                let expression = expression.as_str()
+                    .ok_or_else(|| anyhow::anyhow!("'default_expression' for column '{}' must be a string", name))?;
+                validate_default_expression(expression)?;
+                definition.push_str(&format!(" DEFAULT ({})", expression.trim()));
+            },
+            (None, None) => {},
+        }
+        
+        if col.get("unique").and_then(Value::as_bool) == Some(true) {
+            definition.push_str(" UNIQUE");
+        }
+        
+        if col.get("primary_key").and_then(Value::as_bool) == Some(true) {
+            primary_key.push(quote_ident(name));
+        }
+        
+        definitions.push(definition);
+    }
+    
+    if !primary_key.is_empty() {
+        definitions.push(format!("PRIMARY KEY ({})", primary_key.join(", ")));
+    }
+    
+    Ok(format!(
+        "CREATE TABLE {}{} (\n    {}\n)",
+        if if_not_exists { "IF NOT EXISTS " } else { "" },
+        quote_qualified_ident(table),
+        definitions.join(",\n    ")
+    ))
+}
+
+async fn handle_schema_create_table(
+    pool: &Pool,
+    table: String,
+    columns_str: String,
+    if_not_exists: bool,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let columns = parse_json_arg(&columns_str, "columns")?;
+    let statement = build_create_table_statement(&table, &columns, if_not_exists)?;
+    
+    // The extended protocol refuses to run more than one statement
+    let client = pool.get().await?;
+    client.execute(&statement, &[]).await?;
+    
+    let result = json!({
+        "table": table,
+        "created": true,
+        "statement": statement
+    });
+    println!("{}", format_output(&result, options.format));
+    
+    Ok(())
+}
+
+async fn handle_schema_indexes(
+    pool: &Pool,
+    table: Option<String>,
+    stats: bool,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let stats_columns = if stats {
+        ",
+            s.idx_scan AS scans,
+            s.idx_tup_read AS tuples_read,
+            s.idx_tup_fetch AS tuples_fetched,
+            pg_relation_size(s.indexrelid) AS size_bytes,
+            pg_size_pretty(pg_relation_size(s.indexrelid)) AS size,
+            (s.idx_scan = 0 AND NOT i.indisunique) AS unused"
+    } else {
+        ""
+    };
+    
+    let order_by = if stats {
+        "s.idx_scan, pg_relation_size(s.indexrelid) DESC"
+    } else {
+        "s.schemaname, s.relname, s.indexrelname"
+    };
+    
+    let query = format!(
+        "SELECT
+            s.schemaname::text AS schema,
+            s.relname::text AS table_name,
+            s.indexrelname::text AS index_name,
+            i.indisunique AS is_unique,
+            i.indisprimary AS is_primary,
+            pg_get_indexdef(s.indexrelid) AS definition{}
+         FROM pg_stat_user_indexes s
+         JOIN pg_index i ON i.indexrelid = s.indexrelid
+         WHERE $1::text IS NULL OR s.relname = $1
+         ORDER BY {}",
+        stats_columns, order_by
+    );
+    
+    let client = pool.get().await?;
+    let results = query_to_json(&client, &query, &[&table]).await?;
+    
+    let result_json = Value::Array(results);
+    println!("{}", format_output(&result_json, options.format));
+    
+    Ok(())
+}
+
+/// History table recording applied migrations
+const MIGRATIONS_TABLE: &str = "_code_tools_migrations";
+
+/// Advisory lock key held while migrations run, so concurrent runners can't interleave
+const MIGRATIONS_LOCK_KEY: i64 = 0x636f_6465_746f_6f6c;
+
+struct MigrationFile {
+    version: i64,
+    name: String,
+    up_path: Option<PathBuf>,
+    down_path: Option<PathBuf>,
+}
+
+struct AppliedMigration {
+    name: String,
+    checksum: String,
+    applied_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Split a migration file name into version, name and whether it's a down migration;
+/// files that don't look like migrations return `None`
+fn parse_migration_file_name(file_name: &str) -> Result<Option<(i64, String, bool)>, anyhow::Error> {
+    let pattern = regex::Regex::new(r"^(\d+)_(.+?)(\.(up|down))?\.sql$").unwrap();
+    let caps = match pattern.captures(file_name) {
+        Some(caps) => caps,
+        None => return Ok(None),
+    };
+    
+    let version: i64 = caps[1].parse()
+        .map_err(|_| anyhow::anyhow!("Invalid migration version in '{}'", file_name))?;
+    let is_down = caps.get(4).map(|m| m.as_str()) == Some("down");
+    
+    Ok(Some((version, caps[2].to_string(), is_down)))
+}
+
+fn load_migration_files(dir: &str) -> Result<BTreeMap<i64, MigrationFile>, anyhow::Error> {
+    let mut migrations: BTreeMap<i64, MigrationFile> = BTreeMap::new();
+    
+    let entries = fs::read_dir(dir)
+        .map_err(|e| anyhow::anyhow!("Failed to read migrations directory '{}': {}", dir, e))?;
+    
+    for entry in entries {
+        let path = entry?.path();
+        let file_name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        let (version, name, is_down) = match parse_migration_file_name(&file_name)? {
+            Some(parsed) => parsed,
+            None => continue,
+        };
+        
+        let migration = migrations.entry(version).or_insert_with(|| MigrationFile {
+            version,
+            name: name.clone(),
+            up_path: None,
+            down_path: None,
+        });
+        
+        if migration.name != name {
+            return Err(anyhow::anyhow!("Conflicting migration names for version {}: '{}' and '{}'",
+                version, migration.name, name));
+        }
+        
+        let slot = if is_down { &mut migration.down_path } else { &mut migration.up_path };
+        if slot.is_some() {
+            return Err(anyhow::anyhow!("Duplicate {} migration for version {}",
+                if is_down { "down" } else { "up" }, version));
+        }
+        *slot = Some(path);
+    }
+    
+    Ok(migrations)
+}
+
+fn migration_checksum(sql: &str) -> String {
+    hex::encode(Sha256::digest(sql.as_bytes()))
+}
+
+fn read_migration(path: &Path) -> Result<String, anyhow::Error> {
+    fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read migration '{}': {}", path.display(), e))
+}
+
+async fn ensure_migrations_table(client: &deadpool_postgres::Client) -> Result<(), anyhow::Error> {
+    client.batch_execute(&format!(
+        "CREATE TABLE IF NOT EXISTS {} (
+            version BIGINT PRIMARY KEY,
+            name TEXT NOT NULL,
+            checksum TEXT NOT NULL,
+            applied_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            execution_ms DOUBLE PRECISION NOT NULL
+        )",
+        MIGRATIONS_TABLE
+    )).await?;
+    
+    Ok(())
+}
+
+/// Read the migration history; a database that has never been migrated has no
+/// history table yet, which counts as no migrations applied
+async fn load_applied_migrations(
+    client: &deadpool_postgres::Client,
+) -> Result<BTreeMap<i64, AppliedMigration>, anyhow::Error> {
+    let exists: bool = client.query_one("SELECT to_regclass($1) IS NOT NULL", &[&MIGRATIONS_TABLE])
+        .await?
+        .get(0);
+    if !exists {
+        return Ok(BTreeMap::new());
+    }
+    
+    let rows = client.query(
+        &format!("SELECT version, name, checksum, applied_at FROM {} ORDER BY version", MIGRATIONS_TABLE),
+        &[],
+    ).await?;
+    
+    Ok(rows.iter()
+        .map(|row| (row.get(0), AppliedMigration {
+            name: row.get(1),
+            checksum: row.get(2),
+            applied_at: row.get(3),
+        }))
+        .collect())
+}
+
+/// Compare applied migrations against the files on disk, returning a description of each mismatch
+fn find_migration_drift(
+    files: &BTreeMap<i64, MigrationFile>,
+    applied: &BTreeMap<i64, AppliedMigration>,
+) -> Result<Vec<Value>, anyhow::Error> {
+    let mut drift = Vec::new();
+    
+    for (version, record) in applied {
+        match files.get(version).and_then(|f| f.up_path.as_ref()) {
+            Some(path) => {
+                let checksum = migration_checksum(&read_migration(path)?);
+                if checksum != record.checksum {
+                    drift.push(json!({
+                        "version": version,
+                        "name": record.name,
+                        "problem": "checksum_mismatch",
+                        "applied_checksum": record.checksum,
+                        "file_checksum": checksum
+                    }));
+                }
+            },
+            None => drift.push(json!({
+                "version": version,
+                "name": record.name,
+                "problem": "file_missing"
+            })),
+        }
+    }
+    
+    Ok(drift)
+}
+
+async fn handle_migrate_up(
+    pool: &Pool,
+    dir: String,
+    target: Option<i64>,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let files = load_migration_files(&dir)?;
+    let mut client = pool.get().await?;
+    
+    client.execute("SELECT pg_advisory_lock($1)", &[&MIGRATIONS_LOCK_KEY]).await?;
+    
+    ensure_migrations_table(&client).await?;
+    let applied = load_applied_migrations(&client).await?;
+    let drift = find_migration_drift(&files, &applied)?;
+    if !drift.is_empty() {
+        client.execute("SELECT pg_advisory_unlock($1)", &[&MIGRATIONS_LOCK_KEY]).await?;
+        return Err(anyhow::anyhow!(
+            "Refusing to migrate: applied migrations differ from files on disk: {}",
+            Value::Array(drift)
+        ));
+    }
+    
+    let mut results = Vec::new();
+    for migration in files.values() {
+        if applied.contains_key(&migration.version) {
+            continue;
+        }
+        if target.is_some_and(|t| migration.version > t) {
+            break;
+        }
+        
+        let up_path = migration.up_path.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Migration {} has no up file", migration.version))?;
+        let sql = read_migration(up_path)?;
+        let checksum = migration_checksum(&sql);
+        
+        let started = Instant::now();
+        let transaction = client.transaction().await?;
+        transaction.batch_execute(&sql).await
+            .map_err(|e| anyhow::anyhow!("Migration {} ({}) failed: {}", migration.version, migration.name, e))?;
+        let execution_ms = started.elapsed().as_secs_f64() * 1000.0;
+        transaction.execute(
+            &format!("INSERT INTO {} (version, name, checksum, execution_ms) VALUES ($1, $2, $3, $4)", MIGRATIONS_TABLE),
+            &[&migration.version, &migration.name, &checksum, &execution_ms],
+        ).await?;
+        transaction.commit().await?;
+        
+        results.push(json!({
+            "version": migration.version,
+            "name": migration.name,
+            "status": "applied",
+            "execution_ms": execution_ms
+        }));
+    }
+    
+    client.execute("SELECT pg_advisory_unlock($1)", &[&MIGRATIONS_LOCK_KEY]).await?;
+    
+    let result_json = Value::Array(results);
+    println!("{}", format_output(&result_json, options.format));
+    
+    Ok(())
+}
+
+async fn handle_migrate_down(
+    pool: &Pool,
+    dir: String,
+    steps: usize,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let files = load_migration_files(&dir)?;
+    let mut client = pool.get().await?;
+    
+    client.execute("SELECT pg_advisory_lock($1)", &[&MIGRATIONS_LOCK_KEY]).await?;
+    
+    let applied = load_applied_migrations(&client).await?;
+    let mut results = Vec::new();
+    
+    for (version, record) in applied.iter().rev().take(steps) {
+        let down_path = files.get(version)
+            .and_then(|f| f.down_path.as_ref())
+            .ok_or_else(|| anyhow::anyhow!("Migration {} ({}) has no down file", version, record.name))?;
+        let sql = read_migration(down_path)?;
+        
+        let started = Instant::now();
+        let transaction = client.transaction().await?;
+        transaction.batch_execute(&sql).await
+            .map_err(|e| anyhow::anyhow!("Rollback of migration {} ({}) failed: {}", version, record.name, e))?;
+        transaction.execute(
+            &format!("DELETE FROM {} WHERE version = $1", MIGRATIONS_TABLE),
+            &[version],
+        ).await?;
+        transaction.commit().await?;
+        
+        results.push(json!({
+            "version": version,
+            "name": record.name,
+            "status": "rolled_back",
+            "execution_ms": started.elapsed().as_secs_f64() * 1000.0
+        }));
+    }
+    
+    client.execute("SELECT pg_advisory_unlock($1)", &[&MIGRATIONS_LOCK_KEY]).await?;
+    
+    let result_json = Value::Array(results);
+    println!("{}", format_output(&result_json, options.format));
+    
+    Ok(())
+}
+
+async fn handle_migrate_status(
+    pool: &Pool,
+    dir: String,
     options: &CommonOptions,
 ) -> Result<(), anyhow::Error> {
-    let query = if let Some(table_name) = table {
-        format!(
-            "SELECT 
-                table_name,
-                table_schema,
-                table_type
-             FROM information_schema.tables 
-             WHERE table_schema NOT IN ('information_schema', 'pg_catalog')
-             AND table_name = '{}'
-             ORDER BY table_schema, table_name",
-            table_name
-        )
-    } else {
-        "SELECT 
-            table_name,
-            table_schema,
-            table_type
-         FROM information_schema.tables 
-         WHERE table_schema NOT IN ('information_schema', 'pg_catalog')
-         ORDER BY table_schema, table_name".to_string()
-    };
-    
+    let files = load_migration_files(&dir)?;
     let client = pool.get().await?;
-    let rows = client.query(&query, &[]).await?;
+    let applied = load_applied_migrations(&client).await?;
     
-    let mut results = Vec::new();
-    for row in rows {
-        results.push(row_to_json(&row)?);
-    }
+    let drifted: BTreeMap<i64, String> = find_migration_drift(&files, &applied)?
+        .into_iter()
+        .filter_map(|d| Some((d["version"].as_i64()?, d["problem"].as_str()?.to_string())))
+        .collect();
+    
+    let mut versions: Vec<i64> = files.keys().chain(applied.keys()).copied().collect();
+    versions.sort_unstable();
+    versions.dedup();
+    
+    let results: Vec<Value> = versions.iter()
+        .map(|version| {
+            let record = applied.get(version);
+            let file = files.get(version);
+            let status = match (drifted.get(version), record) {
+                (Some(problem), _) => problem.as_str(),
+                (None, Some(_)) => "applied",
+                (None, None) => "pending",
+            };
+            
+            json!({
+                "version": version,
+                "name": record.map(|r| r.name.clone())
+                    .or_else(|| file.map(|f| f.name.clone())),
+                "status": status,
+                "applied_at": record.map(|r| r.applied_at.to_rfc3339()),
+                "has_down": file.is_some_and(|f| f.down_path.is_some())
+            })
+        })
+        .collect();
     
     let result_json = Value::Array(results);
     println!("{}", format_output(&result_json, options.format));
@@ -577,6 +4905,158 @@ async fn handle_schema_tables(
     Ok(())
 }
 
+fn handle_migrate_new(
+    dir: String,
+    name: String,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    fs::create_dir_all(&dir)
+        .map_err(|e| anyhow::anyhow!("Failed to create migrations directory '{}': {}", dir, e))?;
+    
+    let next_version = load_migration_files(&dir)?
+        .keys()
+        .next_back()
+        .map_or(1, |v| v + 1);
+    
+    let slug: String = name.trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if slug.is_empty() {
+        return Err(anyhow::anyhow!("Migration name must not be empty"));
+    }
+    
+    let base = format!("{:04}_{}", next_version, slug);
+    let up_path = Path::new(&dir).join(format!("{}.up.sql", base));
+    let down_path = Path::new(&dir).join(format!("{}.down.sql", base));
+    
+    fs::write(&up_path, format!("-- Migration {}: {}\n", next_version, name))?;
+    fs::write(&down_path, format!("-- Rollback for migration {}: {}\n", next_version, name))?;
+    
+    let result = json!({
+        "version": next_version,
+        "name": slug,
+        "up": up_path.display().to_string(),
+        "down": down_path.display().to_string()
+    });
+    println!("{}", format_output(&result, options.format));
+    
+    Ok(())
+}
+
+/// A connection outside the pool that keeps the server's notices and
+/// notifications, which pooled connections discard
+struct DedicatedConnection {
+    client: tokio_postgres::Client,
+    messages: tokio::sync::mpsc::UnboundedReceiver<tokio_postgres::AsyncMessage>,
+    task: tokio::task::JoinHandle<Result<(), anyhow::Error>>,
+}
+
+impl DedicatedConnection {
+    async fn connect(config: &Config) -> Result<Self, anyhow::Error> {
+        let pg_config = config.get_pg_config()
+            .map_err(|e| anyhow::anyhow!("Invalid connection settings: {}", e))?;
+        let (client, mut connection) = pg_config.connect(NoTls).await?;
+        
+        // The connection yields asynchronous messages while driving the
+        // protocol, so forward them from a background task
+        let (sender, messages) = tokio::sync::mpsc::unbounded_channel();
+        let task = tokio::spawn(async move {
+            let stream = futures::stream::poll_fn(move |cx| connection.poll_message(cx));
+            pin_mut!(stream);
+            while let Some(message) = stream.next().await {
+                let message = message.map_err(|e| anyhow::anyhow!("Connection lost: {}", e))?;
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+            Ok(())
+        });
+        
+        Ok(DedicatedConnection { client, messages, task })
+    }
+}
+
+/// Take the notices received so far. The connection task forwards a notice
+/// before the response that follows it, so after a statement completes its
+/// notices are already queued
+fn drain_notices(messages: &mut tokio::sync::mpsc::UnboundedReceiver<tokio_postgres::AsyncMessage>) -> Vec<Value> {
+    let mut notices = Vec::new();
+    while let Ok(message) = messages.try_recv() {
+        if let tokio_postgres::AsyncMessage::Notice(notice) = message {
+            notices.push(db_error_to_json(&notice));
+        }
+    }
+    notices
+}
+
+/// Hold a dedicated connection and print notifications as they arrive
+async fn handle_listen_command(
+    connection_config: &Config,
+    channels: Vec<String>,
+    count: Option<usize>,
+) -> Result<(), anyhow::Error> {
+    let mut connection = DedicatedConnection::connect(connection_config).await?;
+    
+    let listen: String = channels.iter()
+        .map(|channel| format!("LISTEN {};", quote_ident(channel)))
+        .collect();
+    connection.client.batch_execute(&listen).await?;
+    eprintln!("Listening on {}", channels.join(", "));
+    
+    // One future for the whole loop, so a Ctrl-C that arrives while a notification
+    // is being printed is still seen on the next iteration
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    
+    let mut received = 0;
+    while count.is_none_or(|count| received < count) {
+        tokio::select! {
+            message = connection.messages.recv() => {
+                // The sender only goes away when the connection does
+                let Some(message) = message else {
+                    return connection.task.await?;
+                };
+                let tokio_postgres::AsyncMessage::Notification(notification) = message else {
+                    continue;
+                };
+                received += 1;
+                let event = json!({
+                    "channel": notification.channel(),
+                    "payload": notification.payload(),
+                    "process_id": notification.process_id(),
+                    "received_at": chrono::Utc::now().to_rfc3339(),
+                });
+                println!("{}", event);
+            },
+            _ = &mut ctrl_c => break,
+        }
+    }
+    
+    Ok(())
+}
+
+async fn handle_notify_command(
+    pool: &Pool,
+    channel: String,
+    payload: Option<String>,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let client = pool.get().await?;
+    let payload = payload.unwrap_or_default();
+    client.execute("SELECT pg_notify($1, $2)", &[&channel, &payload]).await?;
+    
+    let result = json!({
+        "channel": channel,
+        "payload": payload,
+        "sent": true,
+    });
+    println!("{}", format_output(&result, options.format));
+    
+    Ok(())
+}
+
 async fn handle_health_command(
     pool: &Pool,
     options: &CommonOptions,
@@ -602,62 +5082,138 @@ async fn main() {
     let options = CommonOptions::new(cli.format, cli.debug);
     options.setup_debug();
     
-    let pool = match create_pool(
+    let connection = connection_config(
         cli.database_url,
         cli.host,
         cli.port,
         cli.database,
         cli.user,
         cli.password,
-    ).await {
+    );
+    let pool = match create_pool(connection.clone()).await {
         Ok(pool) => pool,
         Err(e) => handle_error(e, "Failed to create database connection pool"),
     };
     
     let result = match cli.command {
-        Commands::Select { query, params, limit } => {
-            handle_select_command(&pool, query, params, limit, &options).await
+        Commands::Select { query, params, limit, stream, fetch_size } => {
+            if stream {
+                handle_select_stream(&pool, query, params, limit, fetch_size, &options).await
+            } else {
+                handle_select_command(&pool, query, params, limit, &options).await
+            }
+        },
+        Commands::Explain { query, params, explain } => {
+            handle_explain_command(&pool, query, params, &explain, &options).await
+        },
+        Commands::Profile { table, sample, estimate, top } => {
+            handle_profile_command(&pool, table, sample, estimate, top, &options).await
+        },
+        Commands::VectorSearch { table, column, vector, limit, metric, select, where_clause } => {
+            let search = VectorSearch { table, column, vector, limit, metric, select, where_clause };
+            handle_vector_search(&pool, search, &options).await
         },
         Commands::Mutate { operation } => {
             match operation {
                 MutateOperation::Insert { table, data, returning } => {
                     handle_mutate_insert(&pool, table, data, returning, &options).await
                 },
-                MutateOperation::Update { table: _table, data: _data, where_clause: _where_clause, returning: _returning } => {
-                    // This is a simplified implementation - full implementation would parse the data JSON
-                    // and construct proper SET clauses
-                    Err(anyhow::anyhow!("Update operation not fully implemented yet"))
+                MutateOperation::Update { table, data, where_clause, returning } => {
+                    handle_mutate_update(&pool, table, data, where_clause, returning, &options).await
                 },
                 MutateOperation::Delete { table, where_clause } => {
-                    let delete_query = format!("DELETE FROM {} WHERE {}", table, where_clause);
-                    handle_execute_command(&pool, delete_query, "[]".to_string(), false, false, &options).await
+                    handle_mutate_delete(&pool, table, where_clause, &options).await
                 },
             }
         },
         Commands::Execute { sql, params, transactional, expect_rows } => {
-            handle_execute_command(&pool, sql, params, transactional, expect_rows, &options).await
+            handle_execute_command(&connection, sql, params, transactional, expect_rows, &options).await
+        },
+        Commands::Script { file, savepoints, continue_on_error, dry_run } => {
+            handle_script_command(&pool, file, savepoints, continue_on_error, dry_run, &options).await
         },
         Commands::Schema { operation } => {
             match operation {
                 SchemaOperation::Tables { table } => {
                     handle_schema_tables(&pool, table, &options).await
                 },
-                SchemaOperation::CreateTable { table: _, columns: _ } => {
-                    Err(anyhow::anyhow!("Create table operation not implemented yet"))
+                SchemaOperation::CreateTable { table, columns, if_not_exists } => {
+                    handle_schema_create_table(&pool, table, columns, if_not_exists, &options).await
+                },
+                SchemaOperation::Indexes { table, stats } => {
+                    handle_schema_indexes(&pool, table, stats, &options).await
+                },
+                SchemaOperation::Snapshot { output, schema } => {
+                    handle_schema_snapshot(&pool, output, schema, &options).await
+                },
+                SchemaOperation::Diff { against_url, snapshot, schema, ddl } => {
+                    handle_schema_diff(&pool, against_url, snapshot, schema, ddl, &options).await
+                },
+            }
+        },
+        Commands::Users { operation } => {
+            match operation {
+                UserOperation::List => {
+                    handle_users_list(&pool, &options).await
+                },
+                UserOperation::Create { username, password, options: user_options } => {
+                    handle_users_create(&pool, username, password, user_options, &options).await
+                },
+                UserOperation::Grant { username, permissions } => {
+                    handle_users_privileges(&pool, username, permissions, true, &options).await
+                },
+                UserOperation::Revoke { username, permissions } => {
+                    handle_users_privileges(&pool, username, permissions, false, &options).await
+                },
+                UserOperation::Drop { username, reassign_to, if_exists } => {
+                    handle_users_drop(&pool, username, reassign_to, if_exists, &options).await
                 },
-                SchemaOperation::Indexes { table: _, stats: _ } => {
-                    Err(anyhow::anyhow!("Index operations not implemented yet"))
+            }
+        },
+        Commands::Monitor { operation } => {
+            match operation {
+                MonitorOperation::Stats => {
+                    handle_monitor_stats(&pool, &options).await
+                },
+                MonitorOperation::Connections => {
+                    handle_monitor_connections(&pool, &options).await
+                },
+                MonitorOperation::SlowQueries { min_duration, limit } => {
+                    handle_monitor_slow_queries(&pool, min_duration, limit, &options).await
+                },
+            }
+        },
+        Commands::Transfer { operation } => {
+            match operation {
+                TransferOperation::Export { table, output, where_clause, limit, file_format } => {
+                    handle_transfer_export(&pool, table, output, where_clause, limit, file_format, &options).await
+                },
+                TransferOperation::Import { table, input, file_format } => {
+                    handle_transfer_import(&pool, table, input, file_format, &options).await
                 },
             }
         },
-        Commands::Users { operation: _ } => {
-            Err(anyhow::anyhow!("User operations not implemented yet"))
+        Commands::Migrate { dir, operation } => {
+            match operation {
+                MigrateOperation::Up { target } => {
+                    handle_migrate_up(&pool, dir, target, &options).await
+                },
+                MigrateOperation::Down { steps } => {
+                    handle_migrate_down(&pool, dir, steps, &options).await
+                },
+                MigrateOperation::Status => {
+                    handle_migrate_status(&pool, dir, &options).await
+                },
+                MigrateOperation::New { name } => {
+                    handle_migrate_new(dir, name, &options)
+                },
+            }
         },
-        Commands::Monitor { operation: _ } => {
-            Err(anyhow::anyhow!("Monitor operations not implemented yet"))
+        Commands::Listen { channels, count } => {
+            handle_listen_command(&connection, channels, count).await
         },
-        Commands::Transfer { operation: _ } => {
-            Err(anyhow::anyhow!("Transfer operations not implemented yet"))
+        Commands::Notify { channel, payload } => {
+            handle_notify_command(&pool, channel, payload, &options).await
         },
         Commands::Health => {
             handle_health_command(&pool, &options).await
@@ -667,4 +5223,732 @@ async fn main() {
     if let Err(e) = result {
         handle_error(e, "Command execution failed");
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Connect with the CLI defaults; these tests need a local PostgreSQL
+    /// and run with `cargo test -- --ignored`
+    fn test_config() -> Config {
+        connection_config(
+            None,
+            "localhost".to_string(),
+            5432,
+            "code_tools_dev".to_string(),
+            "dev_user".to_string(),
+            "dev_password_123".to_string(),
+        )
+    }
+    
+    async fn test_pool() -> Pool {
+        create_pool(test_config()).await.unwrap()
+    }
+    
+    async fn select_value(pool: &Pool, expr: &str) -> Value {
+        let client = pool.get().await.unwrap();
+        let row = client.query_one(&format!("SELECT {} AS v", expr), &[]).await.unwrap();
+        postgres_value_to_json(&row, 0, row.columns()[0].type_()).unwrap()
+    }
+    
+    fn numeric_bytes(ndigits: i16, weight: i16, sign: u16, dscale: u16, digits: &[i16]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&ndigits.to_be_bytes());
+        buf.extend_from_slice(&weight.to_be_bytes());
+        buf.extend_from_slice(&sign.to_be_bytes());
+        buf.extend_from_slice(&dscale.to_be_bytes());
+        for digit in digits {
+            buf.extend_from_slice(&digit.to_be_bytes());
+        }
+        buf
+    }
+    
+    #[test]
+    fn test_decode_numeric() {
+        // 12345.678 = [1, 2345, 6780] with weight 1
+        let raw = numeric_bytes(3, 1, 0x0000, 3, &[1, 2345, 6780]);
+        assert_eq!(decode_pg_numeric(&raw).unwrap(), "12345.678");
+        
+        // -0.00012 = [1, 2000] with weight -1
+        let raw = numeric_bytes(2, -1, 0x4000, 5, &[1, 2000]);
+        assert_eq!(decode_pg_numeric(&raw).unwrap(), "-0.00012");
+        
+        // 100.000 with trailing zero scale
+        let raw = numeric_bytes(1, 0, 0x0000, 3, &[100]);
+        assert_eq!(decode_pg_numeric(&raw).unwrap(), "100.000");
+        
+        let raw = numeric_bytes(0, 0, 0xC000, 0, &[]);
+        assert_eq!(decode_pg_numeric(&raw).unwrap(), "NaN");
+    }
+    
+    #[test]
+    fn test_encode_numeric_round_trip() {
+        for input in ["0", "12345.678", "-0.00012", "100.000", "98765432109876543210.5", "NaN"] {
+            let mut buf = BytesMut::new();
+            encode_pg_numeric(input, &mut buf).unwrap();
+            assert_eq!(decode_pg_numeric(&buf).unwrap(), input);
+        }
+        assert!(encode_pg_numeric("12abc", &mut BytesMut::new()).is_err());
+    }
+    
+    #[test]
+    fn test_money_cents() {
+        assert_eq!(money_cents("12.34").unwrap(), 1234);
+        assert_eq!(money_cents("7").unwrap(), 700);
+        assert_eq!(money_cents("1.5").unwrap(), 150);
+        assert_eq!(money_cents("1.999").unwrap(), 200);
+        assert_eq!(money_cents("1.994").unwrap(), 199);
+        assert_eq!(money_cents("-0.005").unwrap(), -1);
+        assert_eq!(money_cents("-2.345").unwrap(), -235);
+        assert!(money_cents("NaN").is_err());
+        assert!(money_cents("92233720368547758.08").is_err());
+        assert_eq!(money_cents("-92233720368547758.08").unwrap(), i64::MIN);
+    }
+    
+    #[test]
+    fn test_summarize_plan() {
+        let plan = json!({
+            "Node Type": "Nested Loop", "Total Cost": 120.0, "Plan Rows": 10,
+            "Actual Total Time": 50.0, "Actual Rows": 4000, "Actual Loops": 1,
+            "Plans": [
+                {"Node Type": "Seq Scan", "Relation Name": "orders", "Schema": "sales", "Total Cost": 20.0, "Plan Rows": 100,
+                 "Actual Total Time": 5.0, "Actual Rows": 100, "Actual Loops": 1},
+                {"Node Type": "Index Scan", "Relation Name": "items", "Index Name": "items_order_id_idx",
+                 "Total Cost": 0.5, "Plan Rows": 1, "Actual Total Time": 0.4, "Actual Rows": 40, "Actual Loops": 100}
+            ]
+        });
+        let mut nodes = Vec::new();
+        summarize_plan(&plan, 0, &mut nodes);
+        
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(nodes[1].schema.as_deref(), Some("sales"));
+        assert_eq!(nodes[2].depth, 1);
+        assert_eq!(nodes[2].index.as_deref(), Some("items_order_id_idx"));
+        // Children's per-loop time is scaled by their loops: 50 - 5 - 0.4 * 100
+        assert_eq!(nodes[0].exclusive_time_ms, Some(5.0));
+        assert_eq!(nodes[0].exclusive_cost, 99.5);
+        assert_eq!(nodes[0].row_estimate_error(), Some((400.0, "underestimate")));
+        assert_eq!(nodes[1].row_estimate_error(), Some((1.0, "overestimate")));
+    }
+    
+    #[test]
+    fn test_diff_schema_snapshots() {
+        let column = |position: i64, column_type: &str, nullable: bool| json!({
+            "position": position, "type": column_type, "nullable": nullable,
+            "default": null, "generated": null, "identity": null,
+        });
+        let current = json!({"tables": {
+            "public.users": {
+                "schema": "public", "name": "users",
+                "columns": {"id": column(1, "integer", false), "email": column(2, "text", true), "legacy": column(3, "text", true)},
+                "indexes": {"users_email_idx": {"definition": "CREATE INDEX users_email_idx ON public.users USING btree (email)"}},
+                "constraints": {"users_pkey": {"type": "primary_key", "definition": "PRIMARY KEY (id)"}},
+            },
+        }});
+        let reference = json!({"tables": {
+            "public.users": {
+                "schema": "public", "name": "users",
+                "columns": {"id": column(1, "bigint", false), "email": column(5, "text", true)},
+                "indexes": {},
+                "constraints": {"users_pkey": {"type": "primary_key", "definition": "PRIMARY KEY (id)"}},
+            },
+            "public.orders": {
+                "schema": "public", "name": "orders",
+                "columns": {"user_id": column(2, "integer", true), "id": {
+                    "position": 1, "type": "integer", "nullable": false,
+                    "default": "nextval('orders_id_seq'::regclass)", "generated": null, "identity": null,
+                }},
+                "indexes": {},
+                "constraints": {"orders_user_fkey": {"type": "foreign_key", "definition": "FOREIGN KEY (user_id) REFERENCES public.users(id)"}},
+            },
+        }});
+        
+        let diff = diff_schema_snapshots(&current, &reference);
+        assert_eq!(diff.report["tables"]["removed"], json!(["public.orders"]));
+        assert_eq!(diff.report["columns"]["added"][0]["column"], "legacy");
+        // Column position alone is not a change
+        assert_eq!(diff.report["columns"]["changed"].as_array().unwrap().len(), 1);
+        assert_eq!(diff.report["in_sync"], false);
+        assert_eq!(diff.ddl, vec![
+            "DROP INDEX \"public\".\"users_email_idx\";",
+            "CREATE TABLE \"public\".\"orders\" (\n    \"id\" serial NOT NULL,\n    \"user_id\" integer\n);",
+            "ALTER TABLE \"public\".\"users\" DROP COLUMN \"legacy\";",
+            "ALTER TABLE \"public\".\"users\" ALTER COLUMN \"id\" TYPE bigint USING \"id\"::bigint;",
+            "ALTER TABLE \"public\".\"orders\" ADD CONSTRAINT \"orders_user_fkey\" FOREIGN KEY (user_id) REFERENCES public.users(id);",
+        ]);
+        
+        assert_eq!(diff_schema_snapshots(&current, &current).report["in_sync"], true);
+    }
+    
+    #[test]
+    fn test_quote_qualified_ident() {
+        assert_eq!(quote_qualified_ident("users"), "\"users\"");
+        assert_eq!(quote_qualified_ident("Sales.Orders"), "\"Sales\".\"Orders\"");
+        assert_eq!(quote_qualified_ident("\"my.schema\".\"odd\"\"name\""), "\"my.schema\".\"odd\"\"name\"");
+        assert_eq!(quote_qualified_ident("public.\"a.b\""), "\"public\".\"a.b\"");
+        assert_eq!(quote_qualified_ident("x\"; DROP TABLE t; --"), "\"x; DROP TABLE t; --\"");
+    }
+    
+    #[test]
+    fn test_build_privilege_statements() {
+        let permissions = json!({
+            "database": ["connect", "temp"],
+            "schemas": {"app": "usage", "\"my.schema\"": ["create"]},
+            "tables": {"app.*": ["select", "insert"], "app.\"odd.name\"": "all privileges"},
+            "sequences": {"app.*": ["usage"]},
+            "roles": ["read\"only"],
+        });
+        assert_eq!(build_privilege_statements("app\"user", &permissions, "my db", true).unwrap(), vec![
+            "GRANT CONNECT, TEMP ON DATABASE \"my db\" TO \"app\"\"user\"",
+            "GRANT \"read\"\"only\" TO \"app\"\"user\"",
+            "GRANT CREATE ON SCHEMA \"my.schema\" TO \"app\"\"user\"",
+            "GRANT USAGE ON SCHEMA \"app\" TO \"app\"\"user\"",
+            "GRANT USAGE ON ALL SEQUENCES IN SCHEMA \"app\" TO \"app\"\"user\"",
+            "GRANT ALL ON TABLE \"app\".\"odd.name\" TO \"app\"\"user\"",
+            "GRANT SELECT, INSERT ON ALL TABLES IN SCHEMA \"app\" TO \"app\"\"user\"",
+        ]);
+        
+        let revoke = build_privilege_statements("u", &json!({"tables": {"public.t": "select"}}), "db", false).unwrap();
+        assert_eq!(revoke, vec!["REVOKE SELECT ON TABLE \"public\".\"t\" FROM \"u\""]);
+        
+        for bad in [
+            json!({"database": ["select"]}),
+            json!({"tables": {"t": ["select; DROP TABLE t"]}}),
+            json!({"tables": {"t": []}}),
+            json!({"tables": {"t": [1]}}),
+            json!({"schemas": {"s": ["usage", "truncate"]}}),
+            json!({"schemas": ["s"]}),
+            json!({"roles": "admin"}),
+            json!({"functions": {"f": ["execute"]}}),
+            json!(["select"]),
+        ] {
+            assert!(build_privilege_statements("u", &bad, "db", true).is_err(), "{}", bad);
+        }
+    }
+    
+    #[test]
+    fn test_build_create_role_statement() {
+        let options = json!({
+            "createdb": true,
+            "superuser": false,
+            "connection_limit": 5,
+            "valid_until": "2030-01-01'; DROP ROLE x; --",
+            "in_roles": ["readers", "odd\"role"],
+            "permissions": {"database": ["connect"]},
+        });
+        assert_eq!(
+            build_create_role_statement("new\"user", "pa'ss", options.as_object().unwrap()).unwrap(),
+            "CREATE ROLE \"new\"\"user\" WITH LOGIN CONNECTION LIMIT 5 CREATEDB \
+             IN ROLE \"readers\", \"odd\"\"role\" NOSUPERUSER \
+             VALID UNTIL '2030-01-01''; DROP ROLE x; --' PASSWORD 'pa''ss'"
+        );
+        
+        let no_login = json!({"login": false});
+        assert_eq!(
+            build_create_role_statement("u", "p", no_login.as_object().unwrap()).unwrap(),
+            "CREATE ROLE \"u\" WITH NOLOGIN PASSWORD 'p'"
+        );
+        
+        for bad in [
+            json!({"superuser": "yes"}),
+            json!({"connection_limit": "5; DROP ROLE x"}),
+            json!({"valid_until": 1}),
+            json!({"in_roles": "admin"}),
+            json!({"in_roles": [1]}),
+            json!({"password": "other"}),
+        ] {
+            assert!(build_create_role_statement("u", "p", bad.as_object().unwrap()).is_err(), "{}", bad);
+        }
+    }
+    
+    #[test]
+    fn test_build_create_table_statement() {
+        let columns = json!([
+            {"name": "id", "type": "bigint", "primary_key": true},
+            {"name": "na\"me", "type": "character varying(20)", "nullable": false, "default": "it's"},
+            {"name": "n", "type": "numeric(10, 2)", "default": 1.5},
+            {"name": "flag", "type": "boolean", "default": false},
+            {"name": "note", "type": "text", "default": null},
+            {"name": "at", "type": "timestamp(3) with time zone", "default_expression": "now()"},
+            {"name": "tags", "type": "public.tag[]", "default_expression": "'{a,b}'::public.tag[]"},
+        ]);
+        assert_eq!(
+            build_create_table_statement("events", &columns, true).unwrap(),
+            "CREATE TABLE IF NOT EXISTS \"events\" (\n    \"id\" bigint,\n    \
+             \"na\"\"me\" character varying(20) NOT NULL DEFAULT 'it''s',\n    \
+             \"n\" numeric(10, 2) DEFAULT 1.5,\n    \
+             \"flag\" boolean DEFAULT false,\n    \
+             \"note\" text DEFAULT NULL,\n    \
+             \"at\" timestamp(3) with time zone DEFAULT (now()),\n    \
+             \"tags\" public.tag[] DEFAULT ('{a,b}'::public.tag[]),\n    \
+             PRIMARY KEY (\"id\")\n)"
+        );
+        
+        for bad_type in ["int references other_table", "int; drop table x", "text collate \"C\"", "int)", ""] {
+            let columns = json!([{"name": "c", "type": bad_type}]);
+            assert!(build_create_table_statement("t", &columns, false).is_err(), "type {:?}", bad_type);
+        }
+        
+        for bad_default in [
+            "now()); DROP TABLE x; --",
+            "now()) , evil int, (1",
+            "1, evil int",
+            "0 -- comment",
+            "'unterminated",
+            "$$x$$",
+            "'\\'); DROP TABLE x; --'",
+        ] {
+            let columns = json!([{"name": "c", "type": "text", "default_expression": bad_default}]);
+            assert!(build_create_table_statement("t", &columns, false).is_err(), "default {:?}", bad_default);
+        }
+        for good_default in ["nextval('s')", "'a,b;c--d'", "coalesce(1, 2)", "E'it\\'s'", "E'\\'); DROP TABLE x; --'", "\"f\"()"] {
+            validate_default_expression(good_default).unwrap();
+        }
+    }
+    
+    #[test]
+    fn test_split_sql_script() {
+        let script = "-- setup; not a statement\n\
+            CREATE TABLE t (id int, note text DEFAULT ';');\n\
+            /* outer /* nested; */ still comment; */\n\
+            INSERT INTO \"odd;name\" VALUES (E'it\\'s; fine', 'a''b;c');\n\
+            CREATE FUNCTION f() RETURNS int AS $body$ BEGIN RETURN 1; END; $body$ LANGUAGE plpgsql;\n\
+            DO $$ BEGIN PERFORM 1; END $$;\n\
+            CREATE FUNCTION g(a int) RETURNS int LANGUAGE sql BEGIN ATOMIC SELECT CASE WHEN a > 0 THEN 1 ELSE 0 END; SELECT a; END;\n\
+            CREATE PROCEDURE p() begin /* ; */ atomic INSERT INTO t VALUES (1, 'end;'); end;\n\
+            SELECT $1::int, a$b FROM t; ;\n\
+            SELECT 2 -- trailing comment";
+        let statements = split_sql_script(script);
+        let sql: Vec<&str> = statements.iter().map(|statement| statement.sql.as_str()).collect();
+        
+        assert_eq!(sql, vec![
+            "CREATE TABLE t (id int, note text DEFAULT ';')",
+            "INSERT INTO \"odd;name\" VALUES (E'it\\'s; fine', 'a''b;c')",
+            "CREATE FUNCTION f() RETURNS int AS $body$ BEGIN RETURN 1; END; $body$ LANGUAGE plpgsql",
+            "DO $$ BEGIN PERFORM 1; END $$",
+            "CREATE FUNCTION g(a int) RETURNS int LANGUAGE sql BEGIN ATOMIC SELECT CASE WHEN a > 0 THEN 1 ELSE 0 END; SELECT a; END",
+            "CREATE PROCEDURE p() begin /* ; */ atomic INSERT INTO t VALUES (1, 'end;'); end",
+            "SELECT $1::int, a$b FROM t",
+            "SELECT 2 -- trailing comment",
+        ]);
+        assert_eq!(statements.iter().map(|statement| statement.line).collect::<Vec<_>>(), vec![2, 4, 5, 6, 7, 8, 9, 10]);
+        
+        assert!(is_transaction_control("COMMIT"));
+        assert!(is_transaction_control("rollback;"));
+        assert!(!is_transaction_control("ROLLBACK TO SAVEPOINT a"));
+        assert!(!is_transaction_control("SELECT 1"));
+    }
+    
+    #[test]
+    fn test_pgvector_round_trip() {
+        let vector_type = Type::new("vector".to_string(), 0, Kind::Simple, "public".to_string());
+        let mut buf = BytesMut::new();
+        encode_pg_param(&json!([0.1, -2.5, 3.0]), &vector_type, &mut buf).unwrap();
+        assert_eq!(&buf[..4], &[0, 3, 0, 0]);
+        assert_eq!(decode_pg_value(&vector_type, &buf).unwrap(), json!([0.1, -2.5, 3.0]));
+        
+        assert_eq!(parse_vector_param(&json!("0.5, 1,-1")).unwrap(), vec![0.5, 1.0, -1.0]);
+        assert_eq!(parse_vector_param(&json!("[1,2]")).unwrap(), vec![1.0, 2.0]);
+        assert!(parse_vector_param(&json!("1,x")).is_err());
+        
+        // halfvec [1.0, -0.5]
+        let halfvec_type = Type::new("halfvec".to_string(), 0, Kind::Simple, "public".to_string());
+        let raw = [0, 2, 0, 0, 0x3c, 0x00, 0xb8, 0x00];
+        assert_eq!(decode_pg_value(&halfvec_type, &raw).unwrap(), json!([1.0, -0.5]));
+        
+        // sparsevec {2:1.5}/5 is stored with 0-based indices
+        let sparse_type = Type::new("sparsevec".to_string(), 0, Kind::Simple, "public".to_string());
+        let mut raw = BytesMut::new();
+        raw.put_i32(5);
+        raw.put_i32(1);
+        raw.put_i32(0);
+        raw.put_i32(1);
+        raw.put_f32(1.5);
+        assert_eq!(
+            decode_pg_value(&sparse_type, &raw).unwrap(),
+            json!({"dimensions": 5, "indices": [1], "values": [1.5]})
+        );
+    }
+    
+    /// Build an int4 array in the binary wire format
+    fn int4_array_bytes(dims: &[i32], elements: &[Option<i32>]) -> BytesMut {
+        let mut raw = BytesMut::new();
+        raw.put_i32(dims.len() as i32);
+        raw.put_i32(elements.iter().any(|e| e.is_none()) as i32);
+        raw.put_u32(Type::INT4.oid());
+        for &dim in dims {
+            raw.put_i32(dim);
+            raw.put_i32(1);
+        }
+        for element in elements {
+            match element {
+                Some(v) => {
+                    raw.put_i32(4);
+                    raw.put_i32(*v);
+                },
+                None => raw.put_i32(-1),
+            }
+        }
+        raw
+    }
+    
+    #[test]
+    fn test_decode_array() {
+        let raw = int4_array_bytes(&[2, 2], &[Some(1), None, Some(3), Some(4)]);
+        assert_eq!(decode_pg_array(&Type::INT4, &raw).unwrap(), json!([[1, null], [3, 4]]));
+        
+        let raw = int4_array_bytes(&[], &[]);
+        assert_eq!(decode_pg_array(&Type::INT4, &raw).unwrap(), json!([]));
+        
+        // Zero-length dimensions decode as an empty array rather than panicking
+        let raw = int4_array_bytes(&[2, 0], &[]);
+        assert_eq!(decode_pg_array(&Type::INT4, &raw).unwrap(), json!([]));
+        
+        // Malformed headers and truncated data are errors
+        assert!(decode_pg_array(&Type::INT4, &int4_array_bytes(&[-1], &[])).is_err());
+        assert!(decode_pg_array(&Type::INT4, &int4_array_bytes(&[i32::MAX, i32::MAX, i32::MAX], &[])).is_err());
+        assert!(decode_pg_array(&Type::INT4, &int4_array_bytes(&[3], &[Some(1)])).is_err());
+        let mut raw = BytesMut::new();
+        raw.put_i32(-1);
+        raw.put_i32(0);
+        raw.put_u32(Type::INT4.oid());
+        assert!(decode_pg_array(&Type::INT4, &raw).is_err());
+    }
+    
+    #[test]
+    fn test_decode_bit() {
+        let bit = |len: i32, bytes: &[u8]| {
+            let mut raw = BytesMut::new();
+            raw.put_i32(len);
+            raw.put_slice(bytes);
+            decode_pg_value(&Type::VARBIT, &raw)
+        };
+        assert_eq!(bit(10, &[0b1010_0000, 0b0100_0000]).unwrap(), json!("1010000001"));
+        assert_eq!(bit(0, &[]).unwrap(), json!(""));
+        assert!(bit(10, &[0xff]).is_err());
+        assert!(bit(-1, &[]).is_err());
+    }
+    
+    #[test]
+    fn test_f16_to_f32() {
+        assert_eq!(f16_to_f32(0x0000), 0.0);
+        assert_eq!(f16_to_f32(0x3555), 0.333_251_95);
+        assert_eq!(f16_to_f32(0x0001), 2f32.powi(-24));
+        assert_eq!(f16_to_f32(0xfc00), f32::NEG_INFINITY);
+        assert!(f16_to_f32(0x7e00).is_nan());
+    }
+    
+    #[test]
+    fn test_parse_param_interval() {
+        assert_eq!(parse_param_interval("P1Y2M3DT4H5M6.5S").unwrap(), (14, 3, 14_706_500_000));
+        assert_eq!(parse_param_interval("PT0S").unwrap(), (0, 0, 0));
+        assert!(parse_param_interval("1 day").is_err());
+    }
+    
+    #[test]
+    fn test_format_interval() {
+        assert_eq!(format_pg_interval(0, 0, 0), "PT0S");
+        assert_eq!(format_pg_interval(14, 3, 14_706_500_000), "P1Y2M3DT4H5M6.5S");
+        assert_eq!(format_pg_interval(0, -1, -1_000_000), "P-1DT-1S");
+    }
+    
+    #[test]
+    fn test_parse_migration_file_name() {
+        assert_eq!(
+            parse_migration_file_name("20240101_create_users.sql").unwrap(),
+            Some((20240101, "create_users".to_string(), false))
+        );
+        assert_eq!(
+            parse_migration_file_name("0002_add.index.up.sql").unwrap(),
+            Some((2, "add.index".to_string(), false))
+        );
+        assert_eq!(
+            parse_migration_file_name("0002_add.index.down.sql").unwrap(),
+            Some((2, "add.index".to_string(), true))
+        );
+        assert_eq!(parse_migration_file_name("README.md").unwrap(), None);
+        assert_eq!(parse_migration_file_name("create_users.sql").unwrap(), None);
+        assert!(parse_migration_file_name("99999999999999999999_huge.sql").is_err());
+    }
+    
+    #[test]
+    fn test_load_migration_files() {
+        let dir = std::env::temp_dir().join(format!("code_tools_migrations_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let dir_str = dir.to_str().unwrap();
+        
+        fs::write(dir.join("0001_init.up.sql"), "").unwrap();
+        fs::write(dir.join("0001_init.down.sql"), "").unwrap();
+        fs::write(dir.join("0002_seed.sql"), "").unwrap();
+        fs::write(dir.join("notes.txt"), "").unwrap();
+        let migrations = load_migration_files(dir_str).unwrap();
+        assert_eq!(migrations.keys().copied().collect::<Vec<_>>(), vec![1, 2]);
+        assert!(migrations[&1].down_path.is_some());
+        assert!(migrations[&2].up_path.is_some() && migrations[&2].down_path.is_none());
+        
+        // A plain .sql file is the up migration, so it collides with an explicit .up.sql
+        fs::write(dir.join("0002_seed.up.sql"), "").unwrap();
+        assert!(load_migration_files(dir_str).map(|_| ()).unwrap_err().to_string().contains("Duplicate up"));
+        fs::remove_file(dir.join("0002_seed.up.sql")).unwrap();
+        
+        fs::write(dir.join("0001_other.sql"), "").unwrap();
+        assert!(load_migration_files(dir_str).map(|_| ()).unwrap_err().to_string().contains("Conflicting"));
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[test]
+    fn test_parse_csv_header() {
+        assert_eq!(parse_csv_header("a,b,c\r\n").unwrap(), vec!["a", "b", "c"]);
+        assert_eq!(
+            parse_csv_header("\u{feff}id,\"first, last\",\"say \"\"hi\"\"\"\n").unwrap(),
+            vec!["id", "first, last", "say \"hi\""]
+        );
+        assert!(parse_csv_header("a,,b").is_err());
+        assert!(parse_csv_header("\"a,b").is_err());
+    }
+    
+    #[test]
+    fn test_migration_checksum() {
+        assert_eq!(
+            migration_checksum(""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            migration_checksum("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+    
+    #[tokio::test]
+    #[ignore = "requires a local PostgreSQL"]
+    async fn test_migrate_status_is_read_only() {
+        let pool = test_pool().await;
+        let client = pool.get().await.unwrap();
+        
+        // Run inside a scratch schema so the check doesn't depend on the dev database's history
+        client.batch_execute(
+            "DROP SCHEMA IF EXISTS code_tools_status_test CASCADE;
+             CREATE SCHEMA code_tools_status_test;
+             SET search_path TO code_tools_status_test"
+        ).await.unwrap();
+        assert!(load_applied_migrations(&client).await.unwrap().is_empty());
+        let created: bool = client.query_one("SELECT to_regclass($1) IS NOT NULL", &[&MIGRATIONS_TABLE])
+            .await.unwrap().get(0);
+        assert!(!created);
+        
+        client.batch_execute("DROP SCHEMA code_tools_status_test CASCADE; RESET search_path").await.unwrap();
+    }
+    
+    #[tokio::test]
+    #[ignore = "requires a local PostgreSQL"]
+    async fn test_scalar_round_trip() {
+        let pool = test_pool().await;
+        
+        let cases = [
+            ("true", json!(true)),
+            ("7::int2", json!(7)),
+            ("-42::int4", json!(-42)),
+            ("9007199254740993::int8", json!(9007199254740993i64)),
+            ("42::oid", json!(42)),
+            ("1.5::float4", json!(1.5)),
+            ("'Infinity'::float8", json!("Infinity")),
+            ("12345678901234567890.000123::numeric", json!("12345678901234567890.000123")),
+            ("12.34::money", json!("12.34")),
+            ("'x'::\"char\"", json!("x")),
+            ("'ab'::char(3)", json!("ab ")),
+            ("'\\xdeadbeef'::bytea", json!("3q2+7w==")),
+            ("'{\"a\": [1, 2]}'::jsonb", json!({"a": [1, 2]})),
+            ("'a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11'::uuid", json!("a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11")),
+            ("'2024-02-29'::date", json!("2024-02-29")),
+            ("'infinity'::date", json!("infinity")),
+            ("'2024-01-02 03:04:05.678'::timestamp", json!("2024-01-02 03:04:05.678")),
+            ("'2024-01-02 03:04:05+02'::timestamptz", json!("2024-01-02T01:04:05+00:00")),
+            ("'13:14:15+05:30'::timetz", json!("13:14:15+05:30")),
+            ("'1 year 2 mons 3 days 04:05:06.5'::interval", json!("P1Y2M3DT4H5M6.5S")),
+            ("'192.168.0.1'::inet", json!("192.168.0.1")),
+            ("'10.0.0.0/8'::cidr", json!("10.0.0.0/8")),
+            ("'08:00:2b:01:02:03'::macaddr", json!("08:00:2b:01:02:03")),
+            ("B'10101'::varbit", json!("10101")),
+            ("'16/B374D848'::pg_lsn", json!("16/B374D848")),
+            ("'(1,2)'::point", json!({"x": 1.0, "y": 2.0})),
+            ("'<(1,1),5>'::circle", json!({"x": 1.0, "y": 1.0, "radius": 5.0})),
+            ("'fat:2A cat:3'::tsvector", json!("'cat':3 'fat':2A")),
+            ("NULL::int4", Value::Null),
+        ];
+        
+        for (expr, expected) in cases {
+            assert_eq!(select_value(&pool, expr).await, expected, "decoding {}", expr);
+        }
+    }
+    
+    #[tokio::test]
+    #[ignore = "requires a local PostgreSQL"]
+    async fn test_container_round_trip() {
+        let pool = test_pool().await;
+        
+        let cases = [
+            ("'{{1,2},{3,NULL}}'::int8[]", json!([[1, 2], [3, null]])),
+            ("'{}'::text[]", json!([])),
+            ("ARRAY[1.5::numeric, NULL]", json!(["1.5", null])),
+            ("'[1,10)'::int4range", json!({"lower": 1, "upper": 10, "lower_inclusive": true, "upper_inclusive": false})),
+            ("'empty'::int4range", json!({"empty": true})),
+            ("'(,5]'::numrange", json!({"lower": null, "upper": "5", "lower_inclusive": false, "upper_inclusive": true})),
+            ("ROW(2, 'y')", json!({"f1": 2, "f2": "y"})),
+        ];
+        
+        for (expr, expected) in cases {
+            assert_eq!(select_value(&pool, expr).await, expected, "decoding {}", expr);
+        }
+    }
+    
+    #[tokio::test]
+    #[ignore = "requires a local PostgreSQL"]
+    async fn test_user_type_round_trip() {
+        let pool = test_pool().await;
+        let client = pool.get().await.unwrap();
+        client.batch_execute(
+            "DROP TYPE IF EXISTS pg_test_mood CASCADE;
+             DROP TYPE IF EXISTS pg_test_pair CASCADE;
+             CREATE TYPE pg_test_mood AS ENUM ('sad', 'happy');
+             CREATE TYPE pg_test_pair AS (a int4, b text[]);"
+        ).await.unwrap();
+        
+        assert_eq!(select_value(&pool, "'happy'::pg_test_mood").await, json!("happy"));
+        assert_eq!(select_value(&pool, "ARRAY['sad'::pg_test_mood]").await, json!(["sad"]));
+        assert_eq!(
+            select_value(&pool, "ROW(1, ARRAY['x'])::pg_test_pair").await,
+            json!({"a": 1, "b": ["x"]})
+        );
+        
+        client.batch_execute("DROP TYPE pg_test_mood; DROP TYPE pg_test_pair;").await.unwrap();
+    }
+    
+    #[tokio::test]
+    #[ignore = "requires a local PostgreSQL"]
+    async fn test_insert_keeps_typed_shaped_jsonb() {
+        let pool = test_pool().await;
+        let client = pool.get().await.unwrap();
+        client.batch_execute("CREATE TEMP TABLE pg_test_docs (doc jsonb)").await.unwrap();
+        
+        // Mutate data is never read as a {"type", "value"} parameter
+        let doc = json!({"type": "x", "value": 1});
+        let inserted = insert_records(&**client, "pg_test_docs", vec![json!({"doc": doc})], Some("doc")).await.unwrap();
+        assert_eq!(inserted, vec![json!({"doc": doc})]);
+        
+        let row = client.query_one("SELECT doc FROM pg_test_docs", &[]).await.unwrap();
+        assert_eq!(postgres_value_to_json(&row, 0, row.columns()[0].type_()).unwrap(), doc);
+    }
+    
+    #[tokio::test]
+    #[ignore = "requires a local PostgreSQL"]
+    async fn test_script_rolls_back_on_error() {
+        let pool = test_pool().await;
+        let options = CommonOptions::new(OutputFormat::Json, false);
+        let path = std::env::temp_dir().join(format!("code_tools_script_{}.sql", std::process::id()));
+        fs::write(&path, "INSERT INTO pg_test_script VALUES (1);\nSELECT 1 / 0;\nINSERT INTO pg_test_script VALUES (2);\n")
+            .unwrap();
+        let file = path.to_str().unwrap().to_string();
+        let count = |pool: Pool| async move {
+            pool.get().await.unwrap()
+                .query_one("SELECT count(*) FROM pg_test_script", &[]).await.unwrap()
+                .get::<_, i64>(0)
+        };
+        
+        pool.get().await.unwrap()
+            .batch_execute("DROP TABLE IF EXISTS pg_test_script; CREATE TABLE pg_test_script (n int)")
+            .await.unwrap();
+        
+        // Savepoints keep the script running, but a failure still rolls everything back
+        assert!(handle_script_command(&pool, file.clone(), true, false, false, &options).await.is_err());
+        assert_eq!(count(pool.clone()).await, 0);
+        
+        assert!(handle_script_command(&pool, file, true, true, false, &options).await.is_err());
+        assert_eq!(count(pool.clone()).await, 2);
+        
+        pool.get().await.unwrap().batch_execute("DROP TABLE pg_test_script").await.unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+    
+    #[tokio::test]
+    #[ignore = "requires a local PostgreSQL"]
+    async fn test_transfer_csv_round_trip() {
+        let pool = test_pool().await;
+        let options = CommonOptions::new(OutputFormat::Json, false);
+        let path = std::env::temp_dir().join(format!("code_tools_transfer_{}.csv", std::process::id()));
+        let path_str = path.to_str().unwrap().to_string();
+        let rows = |pool: Pool| async move {
+            pool.get().await.unwrap()
+                .query("SELECT a, b, doubled FROM pg_test_transfer ORDER BY a", &[]).await.unwrap()
+                .iter()
+                .map(|row| (row.get::<_, i32>(0), row.get::<_, String>(1), row.get::<_, i32>(2)))
+                .collect::<Vec<_>>()
+        };
+        
+        pool.get().await.unwrap().batch_execute(
+            "DROP TABLE IF EXISTS pg_test_transfer;
+             CREATE TABLE pg_test_transfer (
+                 id int GENERATED ALWAYS AS IDENTITY,
+                 a int NOT NULL,
+                 b text NOT NULL,
+                 doubled int GENERATED ALWAYS AS (a * 2) STORED
+             );
+             INSERT INTO pg_test_transfer (a, b) VALUES (1, 'one'), (2, 'two, too')"
+        ).await.unwrap();
+        
+        handle_transfer_export(&pool, "pg_test_transfer".to_string(), path_str.clone(), None, None, None, &options)
+            .await.unwrap();
+        assert!(fs::read_to_string(&path).unwrap().starts_with("a,b\n"));
+        
+        pool.get().await.unwrap().batch_execute("TRUNCATE pg_test_transfer").await.unwrap();
+        handle_transfer_import(&pool, "pg_test_transfer".to_string(), path_str.clone(), None, &options)
+            .await.unwrap();
+        assert_eq!(rows(pool.clone()).await, vec![
+            (1, "one".to_string(), 2),
+            (2, "two, too".to_string(), 4),
+        ]);
+        
+        // Columns are matched by the header, not by position
+        fs::write(&path, "b,a\nthree,3\n").unwrap();
+        handle_transfer_import(&pool, "pg_test_transfer".to_string(), path_str, None, &options)
+            .await.unwrap();
+        assert_eq!(rows(pool.clone()).await[2], (3, "three".to_string(), 6));
+        
+        pool.get().await.unwrap().batch_execute("DROP TABLE pg_test_transfer").await.unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+    
+    #[tokio::test]
+    #[ignore = "requires a local PostgreSQL"]
+    async fn test_run_execute_statement() {
+        let mut connection = DedicatedConnection::connect(&test_config()).await.unwrap();
+        
+        let result = run_execute_statement(
+            &connection.client, &mut connection.messages,
+            "DO $$ BEGIN RAISE NOTICE 'first'; RAISE WARNING 'second'; END $$", &[], false,
+        ).await.unwrap();
+        assert_eq!(result["command"], "DO");
+        assert_eq!(result["notices"][0]["message"], "first");
+        assert_eq!(result["notices"][1]["severity"], "WARNING");
+        assert!(result.get("columns").is_none());
+        // The statement's notices were already taken
+        assert!(drain_notices(&mut connection.messages).is_empty());
+        
+        let params = data_to_sql_params(vec![json!(41)]);
+        let result = run_execute_statement(
+            &connection.client, &mut connection.messages,
+            "SELECT $1::int4 + 1 AS n", &params, true,
+        ).await.unwrap();
+        assert_eq!(result["affected_rows"], 1);
+        assert_eq!(result["columns"][0]["name"], "n");
+        assert_eq!(result["rows"], json!([{"n": 42}]));
+        assert_eq!(result["notices"], json!([]));
+        
+        let error = run_execute_statement(
+            &connection.client, &mut connection.messages,
+            "SELECT 1 / 0", &[], false,
+        ).await.unwrap_err();
+        assert_eq!(execute_error_to_json(&error)["code"], "22012");
+    }
+}
@@ -1,6 +1,6 @@
 #!/usr/bin/env cargo run --bin redis --
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use redis::{aio::ConnectionManager, AsyncCommands, Client, RedisResult};
 use serde_json::json;
 
@@ -41,12 +41,22 @@ enum Commands {
         /// Expiration time in seconds (optional)
         #[arg(short, long)]
         expire: Option<u64>,
+        
+        #[command(flatten)]
+        value_options: ValueOptions,
     },
     
     /// Get value by key
     Get {
         /// Key name
         key: String,
+        
+        /// Key type to read; auto detects it with TYPE
+        #[arg(long = "type", value_enum, default_value = "string")]
+        key_type: GetType,
+        
+        #[command(flatten)]
+        value_options: ValueOptions,
     },
     
     /// Delete one or more keys
@@ -61,9 +71,8 @@ enum Commands {
         #[arg(default_value = "*")]
         pattern: String,
         
-        /// Limit number of results
-        #[arg(short, long)]
-        limit: Option<usize>,
+        #[command(flatten)]
+        list: ListOptions,
     },
     
     /// Set expiration for a key
@@ -99,6 +108,137 @@ enum Commands {
         operation: SetOperation,
     },
     
+    /// Sorted set operations
+    Zset {
+        #[command(subcommand)]
+        operation: SortedSetOperation,
+    },
+    
+    /// Stream operations
+    Stream {
+        #[command(subcommand)]
+        operation: StreamOperation,
+    },
+    
+    /// Publish a message to a channel
+    Publish {
+        /// Channel name
+        channel: String,
+        
+        /// Message to publish
+        message: String,
+    },
+    
+    /// Subscribe to channels and print messages as NDJSON
+    Subscribe {
+        /// Channel names; names containing *, ? or [ are subscribed as patterns
+        #[arg(required = true)]
+        channels: Vec<String>,
+        
+        /// Exit after receiving this many messages
+        #[arg(short, long)]
+        count: Option<usize>,
+        
+        /// Exit after this many seconds
+        #[arg(short, long)]
+        timeout: Option<u64>,
+    },
+    
+    /// Run a Lua script, cached on the server via SCRIPT LOAD/EVALSHA
+    Eval {
+        /// Script source, or @path to read it from a file
+        script: String,
+        
+        /// Key names passed as KEYS
+        #[arg(long, num_args = 1..)]
+        keys: Vec<String>,
+        
+        /// Arguments passed as ARGV
+        #[arg(long, num_args = 1.., allow_negative_numbers = true)]
+        args: Vec<String>,
+    },
+    
+    /// Run a JSON list of commands atomically with MULTI/EXEC
+    Tx {
+        /// JSON file: [["SET", "k", "v"], ...] or {"watch": [...], "commands": [...]}
+        file: String,
+        
+        /// Keys to WATCH; the transaction aborts if any of them changes first
+        #[arg(long, num_args = 1..)]
+        watch: Vec<String>,
+    },
+    
+    /// Export matching keys to NDJSON (key, type, TTL and value or DUMP payload, always DUMP for streams)
+    Export {
+        /// Pattern to match keys
+        #[arg(long, default_value = "*")]
+        pattern: String,
+        
+        /// Output file (default: stdout)
+        #[arg(short, long)]
+        output: Option<String>,
+        
+        /// Store every key as a base64 DUMP payload (only restorable on a compatible Redis version)
+        #[arg(long)]
+        dump: bool,
+        
+        /// SCAN COUNT hint, also used as the pipeline batch size
+        #[arg(long, default_value = "100")]
+        count: usize,
+        
+        /// Only export keys of this type
+        #[arg(long = "type")]
+        key_type: Option<String>,
+    },
+    
+    /// Import keys from an NDJSON export
+    Import {
+        /// Export file (- for stdin)
+        file: String,
+        
+        /// Overwrite keys that already exist instead of reporting them as conflicts
+        #[arg(long)]
+        replace: bool,
+    },
+    
+    /// Report memory usage by key prefix and type, big keys and keys without TTL
+    Analyze {
+        /// Pattern to match keys
+        #[arg(default_value = "*")]
+        pattern: String,
+        
+        #[command(flatten)]
+        analyze: AnalyzeOptions,
+    },
+    
+    /// Distributed lock operations
+    Lock {
+        #[command(subcommand)]
+        operation: LockOperation,
+    },
+    
+    /// Check and consume a rate limit; exits non-zero when the request is denied
+    RateLimit {
+        /// Rate limit key
+        key: String,
+        
+        /// Requests allowed per window (bucket capacity for token-bucket)
+        #[arg(short, long)]
+        limit: u64,
+        
+        /// Window length in seconds (time to refill an empty bucket for token-bucket)
+        #[arg(short, long)]
+        window: f64,
+        
+        /// Rate limiting algorithm
+        #[arg(short, long, value_enum, default_value = "sliding-window")]
+        algorithm: RateLimitAlgorithm,
+        
+        /// Units this request consumes
+        #[arg(long, default_value = "1")]
+        cost: u64,
+    },
+    
     /// Get Redis server information
     Info {
         /// Specific info section (optional)
@@ -115,6 +255,138 @@ enum Commands {
     Health,
 }
 
+/// How string values are encoded in Redis
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum ValueCodec {
+    /// Store the value as given
+    Raw,
+    /// Value is given and shown as base64, stored as the decoded bytes
+    Base64,
+    /// Value is given and shown as JSON, stored as MessagePack
+    Msgpack,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum RateLimitAlgorithm {
+    /// At most `limit` requests within any trailing window (sorted set of timestamps)
+    SlidingWindow,
+    /// Bucket of `limit` tokens refilled continuously over the window
+    TokenBucket,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum GetType {
+    String,
+    Auto,
+}
+
+#[derive(clap::Args)]
+struct ValueOptions {
+    /// Treat the value as JSON: validated on set, decoded on get
+    #[arg(long, conflicts_with = "codec")]
+    json: bool,
+    
+    /// Value encoding
+    #[arg(long, value_enum, default_value = "raw")]
+    codec: ValueCodec,
+}
+
+impl ValueOptions {
+    /// Turn a value given on the command line into the bytes to store
+    fn encode(&self, value: &str) -> Result<Vec<u8>, anyhow::Error> {
+        use base64::prelude::*;
+        
+        if self.json {
+            let parsed: serde_json::Value = serde_json::from_str(value)
+                .map_err(|e| anyhow::anyhow!("Value is not valid JSON: {}", e))?;
+            return Ok(serde_json::to_vec(&parsed)?);
+        }
+        match self.codec {
+            ValueCodec::Raw => Ok(value.as_bytes().to_vec()),
+            ValueCodec::Base64 => BASE64_STANDARD.decode(value.trim())
+                .map_err(|e| anyhow::anyhow!("Value is not valid base64: {}", e)),
+            ValueCodec::Msgpack => {
+                let parsed: serde_json::Value = serde_json::from_str(value)
+                    .map_err(|e| anyhow::anyhow!("Value is not valid JSON: {}", e))?;
+                Ok(rmp_serde::to_vec(&parsed)?)
+            }
+        }
+    }
+    
+    /// Turn stored bytes back into a JSON value for output
+    fn decode(&self, bytes: &[u8]) -> Result<serde_json::Value, anyhow::Error> {
+        use base64::prelude::*;
+        
+        if self.json {
+            return serde_json::from_slice(bytes)
+                .map_err(|e| anyhow::anyhow!("Stored value is not valid JSON: {}", e));
+        }
+        match self.codec {
+            ValueCodec::Raw => match std::str::from_utf8(bytes) {
+                Ok(text) => Ok(json!(text)),
+                Err(_) => Err(anyhow::anyhow!("Stored value is not valid UTF-8, use --codec base64")),
+            },
+            ValueCodec::Base64 => Ok(json!(BASE64_STANDARD.encode(bytes))),
+            ValueCodec::Msgpack => rmp_serde::from_slice(bytes)
+                .map_err(|e| anyhow::anyhow!("Stored value is not valid MessagePack: {}", e)),
+        }
+    }
+}
+
+#[derive(clap::Args)]
+struct ListOptions {
+    /// Limit number of results
+    #[arg(short, long)]
+    limit: Option<usize>,
+    
+    /// SCAN COUNT hint (keys examined per iteration)
+    #[arg(long, default_value = "100")]
+    count: usize,
+    
+    /// Only return keys of this type (string, list, set, zset, hash, stream)
+    #[arg(long = "type")]
+    key_type: Option<String>,
+    
+    /// Include each key's type
+    #[arg(long)]
+    with_type: bool,
+    
+    /// Include each key's TTL in seconds
+    #[arg(long)]
+    with_ttl: bool,
+    
+    /// Include each key's memory usage in bytes
+    #[arg(long)]
+    with_memory: bool,
+}
+
+#[derive(clap::Args)]
+struct AnalyzeOptions {
+    /// Fraction of scanned keys to inspect (0 < rate <= 1); totals are extrapolated
+    #[arg(long, default_value = "1.0")]
+    sample_rate: f64,
+    
+    /// Stop after inspecting this many keys
+    #[arg(long)]
+    max_keys: Option<usize>,
+    
+    /// Delimiter separating key prefix segments
+    #[arg(long, default_value = ":")]
+    delimiter: String,
+    
+    /// Number of leading segments that form a prefix
+    #[arg(long, default_value = "1")]
+    depth: usize,
+    
+    /// Number of biggest keys to report
+    #[arg(long, default_value = "10")]
+    top: usize,
+    
+    /// SCAN COUNT hint (keys examined per iteration)
+    #[arg(long, default_value = "100")]
+    count: usize,
+}
+
 #[derive(Subcommand)]
 enum HashOperation {
     /// Get hash field value
@@ -238,6 +510,323 @@ enum SetOperation {
     },
 }
 
+#[derive(Subcommand)]
+enum SortedSetOperation {
+    /// Add members with scores (ZADD)
+    Add {
+        /// Sorted set key
+        key: String,
+        /// Score/member pairs: score member [score member ...]
+        #[arg(required = true, num_args = 2.., allow_negative_numbers = true)]
+        members: Vec<String>,
+        #[command(flatten)]
+        add: ZAddOptions,
+    },
+    
+    /// Get members by rank, score or lexicographical range (ZRANGE)
+    Range {
+        /// Sorted set key
+        key: String,
+        /// Range start (rank, score such as (1 or -inf, or lex bound such as [a or -)
+        #[arg(default_value = "0", allow_hyphen_values = true)]
+        start: String,
+        /// Range end (rank, score such as 5 or +inf, or lex bound such as (z or +)
+        #[arg(default_value = "-1", allow_hyphen_values = true)]
+        stop: String,
+        #[command(flatten)]
+        range: ZRangeOptions,
+    },
+    
+    /// Remove members (ZREM)
+    Remove {
+        /// Sorted set key
+        key: String,
+        /// Members to remove
+        #[arg(required = true)]
+        members: Vec<String>,
+    },
+    
+    /// Increment a member's score (ZINCRBY)
+    IncrBy {
+        /// Sorted set key
+        key: String,
+        /// Amount to add to the score
+        #[arg(allow_negative_numbers = true)]
+        increment: f64,
+        /// Member name
+        member: String,
+    },
+    
+    /// Get a member's rank and score (ZRANK/ZREVRANK)
+    Rank {
+        /// Sorted set key
+        key: String,
+        /// Member name
+        member: String,
+        /// Rank from the highest score instead of the lowest
+        #[arg(long)]
+        rev: bool,
+    },
+    
+    /// Get sorted set cardinality (ZCARD)
+    Card {
+        /// Sorted set key
+        key: String,
+    },
+    
+    /// Remove and return the members with the lowest scores (ZPOPMIN)
+    PopMin {
+        /// Sorted set key
+        key: String,
+        /// Number of members to pop
+        #[arg(short, long, default_value = "1")]
+        count: usize,
+    },
+    
+    /// Remove and return the members with the highest scores (ZPOPMAX)
+    PopMax {
+        /// Sorted set key
+        key: String,
+        /// Number of members to pop
+        #[arg(short, long, default_value = "1")]
+        count: usize,
+    },
+}
+
+#[derive(clap::Args)]
+struct ZAddOptions {
+    /// Only add new members, never update existing ones
+    #[arg(long, conflicts_with_all = ["xx", "gt", "lt"])]
+    nx: bool,
+    
+    /// Only update existing members, never add new ones
+    #[arg(long)]
+    xx: bool,
+    
+    /// Only update a member if the new score is greater
+    #[arg(long, conflicts_with = "lt")]
+    gt: bool,
+    
+    /// Only update a member if the new score is less
+    #[arg(long)]
+    lt: bool,
+    
+    /// Count changed members, not only added ones
+    #[arg(long)]
+    ch: bool,
+}
+
+#[derive(clap::Args)]
+struct ZRangeOptions {
+    /// Interpret start/stop as scores
+    #[arg(long, conflicts_with = "by_lex")]
+    by_score: bool,
+    
+    /// Interpret start/stop as lexicographical bounds
+    #[arg(long, conflicts_with = "withscores")]
+    by_lex: bool,
+    
+    /// Return members from the highest score to the lowest (score and lex ranges then start at the upper bound)
+    #[arg(long)]
+    rev: bool,
+    
+    /// Skip OFFSET matches and return at most COUNT (with --by-score or --by-lex)
+    #[arg(long, num_args = 2, value_names = ["OFFSET", "COUNT"], allow_negative_numbers = true)]
+    limit: Option<Vec<i64>>,
+    
+    /// Include each member's score
+    #[arg(long)]
+    withscores: bool,
+}
+
+#[derive(Subcommand)]
+enum LockOperation {
+    /// Acquire a lock (SET NX PX); exits non-zero when it is held by someone else
+    Acquire {
+        /// Lock key
+        key: String,
+        /// Lock lifetime in seconds
+        #[arg(short, long, default_value = "30")]
+        ttl: f64,
+        /// Owner token (default: random); needed to release or extend the lock
+        #[arg(long)]
+        token: Option<String>,
+        /// Keep retrying for up to this many seconds
+        #[arg(short, long)]
+        wait: Option<f64>,
+        /// Delay between retries in milliseconds
+        #[arg(long, default_value = "100")]
+        retry_interval: u64,
+    },
+    
+    /// Release a lock if it is still held with the given token
+    Release {
+        /// Lock key
+        key: String,
+        /// Owner token returned by acquire
+        token: String,
+    },
+    
+    /// Reset a lock's lifetime if it is still held with the given token
+    Extend {
+        /// Lock key
+        key: String,
+        /// Owner token returned by acquire
+        token: String,
+        /// New lock lifetime in seconds
+        #[arg(short, long, default_value = "30")]
+        ttl: f64,
+    },
+}
+
+#[derive(Subcommand)]
+enum StreamOperation {
+    /// Append an entry to a stream (XADD)
+    Add {
+        /// Stream key
+        key: String,
+        /// Field/value pairs: field value [field value ...]
+        #[arg(required = true, num_args = 2..)]
+        fields: Vec<String>,
+        /// Entry ID (default: auto-generated)
+        #[arg(long, default_value = "*")]
+        id: String,
+        /// Trim the stream to at most this many entries
+        #[arg(long)]
+        maxlen: Option<u64>,
+        /// Let Redis trim approximately (~), which is cheaper
+        #[arg(long)]
+        approximate: bool,
+    },
+    
+    /// Get entries by ID range (XRANGE/XREVRANGE)
+    Range {
+        /// Stream key
+        key: String,
+        /// Start ID (- for the first entry)
+        #[arg(default_value = "-")]
+        start: String,
+        /// End ID (+ for the last entry)
+        #[arg(default_value = "+")]
+        end: String,
+        /// Maximum number of entries
+        #[arg(short, long)]
+        count: Option<usize>,
+        /// Return entries newest first
+        #[arg(long)]
+        reverse: bool,
+    },
+    
+    /// Read entries after an ID from one or more streams (XREAD)
+    Read {
+        /// Stream keys
+        #[arg(required = true)]
+        keys: Vec<String>,
+        /// Read entries after this ID ($ for new entries only; default: 0, or $ with --follow)
+        #[arg(long)]
+        id: Option<String>,
+        #[command(flatten)]
+        read: StreamReadOptions,
+        /// Keep reading and print entries as NDJSON until interrupted
+        #[arg(long)]
+        follow: bool,
+    },
+    
+    /// Create a consumer group (XGROUP CREATE)
+    GroupCreate {
+        /// Stream key
+        key: String,
+        /// Group name
+        group: String,
+        /// Deliver entries after this ID ($ for new entries only, 0 for all)
+        #[arg(long, default_value = "$")]
+        id: String,
+        /// Create the stream if it does not exist
+        #[arg(long)]
+        mkstream: bool,
+    },
+    
+    /// Read entries as a consumer group member (XREADGROUP)
+    ReadGroup {
+        #[command(flatten)]
+        member: GroupConsumer,
+        /// Stream keys
+        #[arg(required = true)]
+        keys: Vec<String>,
+        /// Read entries after this ID (> for never-delivered entries, 0 for own pending entries)
+        #[arg(long, default_value = ">")]
+        id: String,
+        #[command(flatten)]
+        read: StreamReadOptions,
+        /// Do not add the entries to the pending list
+        #[arg(long)]
+        noack: bool,
+    },
+    
+    /// Acknowledge processed entries (XACK)
+    Ack {
+        /// Stream key
+        key: String,
+        /// Group name
+        group: String,
+        /// Entry IDs to acknowledge
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+    
+    /// Inspect pending entries of a group (XPENDING)
+    Pending {
+        /// Stream key
+        key: String,
+        /// Group name
+        group: String,
+        /// List individual entries, at most this many (default: summary only)
+        #[arg(short, long)]
+        count: Option<usize>,
+        /// Only list entries owned by this consumer
+        #[arg(long, requires = "count")]
+        consumer: Option<String>,
+        /// Only list entries idle for at least this many milliseconds
+        #[arg(long, requires = "count")]
+        idle: Option<u64>,
+    },
+    
+    /// Trim a stream by length or minimum ID (XTRIM)
+    Trim {
+        /// Stream key
+        key: String,
+        /// Keep at most this many entries
+        #[arg(long, conflicts_with = "minid", required_unless_present = "minid")]
+        maxlen: Option<u64>,
+        /// Evict entries with IDs lower than this
+        #[arg(long)]
+        minid: Option<String>,
+        /// Let Redis trim approximately (~), which is cheaper
+        #[arg(long)]
+        approximate: bool,
+    },
+}
+
+#[derive(clap::Args)]
+struct StreamReadOptions {
+    /// Maximum number of entries per stream and call
+    #[arg(short, long)]
+    count: Option<usize>,
+    
+    /// Block for up to this many milliseconds waiting for entries
+    #[arg(long)]
+    block: Option<u64>,
+}
+
+#[derive(clap::Args)]
+struct GroupConsumer {
+    /// Group name
+    group: String,
+    
+    /// Consumer name
+    consumer: String,
+}
+
 async fn create_connection_manager(url: &str) -> Result<ConnectionManager, anyhow::Error> {
     let client = Client::open(url)?;
     let manager = client.get_connection_manager().await?;
@@ -249,9 +838,11 @@ async fn handle_set_command(
     key: String,
     value: String,
     expire: Option<u64>,
+    value_options: ValueOptions,
     options: &CommonOptions,
 ) -> Result<(), anyhow::Error> {
-    let result: RedisResult<String> = manager.set(&key, &value).await;
+    let bytes = value_options.encode(&value)?;
+    let result: RedisResult<String> = manager.set(&key, &bytes).await;
     
     match result {
         Ok(_) => {
@@ -259,10 +850,17 @@ async fn handle_set_command(
                 let _: RedisResult<i32> = manager.expire(&key, exp_seconds as i64).await;
             }
             
+            // Echo JSON values as parsed structure rather than the input text
+            let value = if value_options.json || value_options.codec == ValueCodec::Msgpack {
+                value_options.decode(&bytes)?
+            } else {
+                json!(value)
+            };
             let response = json!({
                 "status": "ok",
                 "key": key,
                 "value": value,
+                "bytes": bytes.len(),
                 "expired": expire
             });
             println!("{}", format_output(&response, options.format));
@@ -276,17 +874,54 @@ async fn handle_set_command(
 async fn handle_get_command(
     manager: &mut ConnectionManager,
     key: String,
+    key_type: GetType,
+    value_options: ValueOptions,
     options: &CommonOptions,
 ) -> Result<(), anyhow::Error> {
-    let result: RedisResult<Option<String>> = manager.get(&key).await;
+    let detected = match key_type {
+        GetType::String => None,
+        GetType::Auto => {
+            let detected: String = redis::cmd("TYPE").arg(&key).query_async(manager).await
+                .map_err(|e| anyhow::anyhow!("Failed to get type of key '{}': {}", key, e))?;
+            Some(detected)
+        }
+    };
+    
+    // Non-string types are read with their own command and returned whole
+    if let Some(detected) = detected.as_deref().filter(|t| *t != "string") {
+        let value = match read_command(&key, detected) {
+            Some(cmd) => {
+                let reply: redis::Value = cmd.query_async(manager).await
+                    .map_err(|e| anyhow::anyhow!("Failed to read key '{}': {}", key, e))?;
+                export_value_to_json(detected, &reply)
+            }
+            None if detected == "none" => serde_json::Value::Null,
+            None => return Err(anyhow::anyhow!("Cannot read key '{}' of type '{}'", key, detected)),
+        };
+        let response = json!({
+            "key": key,
+            "type": if detected == "none" { None } else { Some(detected) },
+            "value": value,
+            "exists": detected != "none"
+        });
+        println!("{}", format_output(&response, options.format));
+        return Ok(());
+    }
+    
+    let result: RedisResult<Option<Vec<u8>>> = manager.get(&key).await;
     
     match result {
-        Ok(Some(value)) => {
-            let response = json!({
+        Ok(Some(bytes)) => {
+            let value = value_options.decode(&bytes)
+                .map_err(|e| anyhow::anyhow!("Failed to decode key '{}': {}", key, e))?;
+            let mut response = json!({
                 "key": key,
                 "value": value,
                 "exists": true
             });
+            if detected.is_some() {
+                response["type"] = json!("string");
+            }
             println!("{}", format_output(&response, options.format));
         }
         Ok(None) => {
@@ -325,29 +960,106 @@ async fn handle_delete_command(
     Ok(())
 }
 
+/// Iterate keys with SCAN, stopping once `limit` keys were collected.
+/// Returns the keys and whether the whole keyspace was scanned.
+async fn scan_keys(
+    manager: &mut ConnectionManager,
+    pattern: &str,
+    count: usize,
+    key_type: Option<&str>,
+    limit: Option<usize>,
+) -> RedisResult<(Vec<String>, bool)> {
+    let mut keys = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    let mut cursor: u64 = 0;
+    
+    loop {
+        let mut cmd = redis::cmd("SCAN");
+        cmd.arg(cursor).arg("MATCH").arg(pattern).arg("COUNT").arg(count);
+        if let Some(key_type) = key_type {
+            cmd.arg("TYPE").arg(key_type);
+        }
+        let (next, batch): (u64, Vec<String>) = cmd.query_async(manager).await?;
+        
+        // SCAN may return a key more than once while the keyspace is rehashed
+        for key in batch {
+            if seen.insert(key.clone()) {
+                keys.push(key);
+            }
+        }
+        cursor = next;
+        
+        if let Some(limit_val) = limit {
+            if keys.len() >= limit_val {
+                keys.truncate(limit_val);
+                return Ok((keys, cursor == 0));
+            }
+        }
+        if cursor == 0 {
+            return Ok((keys, true));
+        }
+    }
+}
+
 async fn handle_list_command(
     manager: &mut ConnectionManager,
     pattern: String,
-    limit: Option<usize>,
+    list: ListOptions,
     options: &CommonOptions,
 ) -> Result<(), anyhow::Error> {
-    let result: RedisResult<Vec<String>> = manager.keys(&pattern).await;
+    let (keys, complete) = scan_keys(manager, &pattern, list.count.max(1), list.key_type.as_deref(), list.limit)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to list keys with pattern '{}': {}", pattern, e))?;
     
-    match result {
-        Ok(mut keys) => {
-            if let Some(limit_val) = limit {
-                keys.truncate(limit_val);
+    let keys_json = if list.with_type || list.with_ttl || list.with_memory {
+        let mut entries = Vec::with_capacity(keys.len());
+        for chunk in keys.chunks(list.count.max(1)) {
+            let mut pipe = redis::pipe();
+            for key in chunk {
+                if list.with_type {
+                    pipe.cmd("TYPE").arg(key);
+                }
+                if list.with_ttl {
+                    pipe.cmd("TTL").arg(key);
+                }
+                if list.with_memory {
+                    pipe.cmd("MEMORY").arg("USAGE").arg(key);
+                }
             }
+            let replies: Vec<redis::Value> = pipe.query_async(manager).await
+                .map_err(|e| anyhow::anyhow!("Failed to fetch key details: {}", e))?;
             
-            let response = json!({
-                "pattern": pattern,
-                "count": keys.len(),
-                "keys": keys
-            });
-            println!("{}", format_output(&response, options.format));
+            let mut replies = replies.into_iter();
+            for key in chunk {
+                let mut entry = json!({ "key": key });
+                if list.with_type {
+                    let key_type: String = redis::from_redis_value(&replies.next().unwrap_or(redis::Value::Nil))?;
+                    entry["type"] = json!(key_type);
+                }
+                if list.with_ttl {
+                    let ttl: i64 = redis::from_redis_value(&replies.next().unwrap_or(redis::Value::Nil))?;
+                    entry["ttl_seconds"] = json!(ttl);
+                }
+                if list.with_memory {
+                    let bytes: Option<i64> = redis::from_redis_value(&replies.next().unwrap_or(redis::Value::Nil))?;
+                    entry["memory_bytes"] = json!(bytes);
+                }
+                entries.push(entry);
+            }
         }
-        Err(e) => return Err(anyhow::anyhow!("Failed to list keys with pattern '{}': {}", pattern, e)),
-    }
+        json!(entries)
+    } else {
+        json!(keys)
+    };
+    
+    let response = json!({
+        "pattern": pattern,
+        "type": list.key_type,
+        "count": keys.len(),
+        "scan_complete": complete,
+        "keys": keys_json
+    });
+    println!("{}", format_output(&response, options.format));
     
     Ok(())
 }
@@ -630,29 +1342,1767 @@ async fn handle_list_range(
     Ok(())
 }
 
-#[tokio::main]
-async fn main() {
-    let cli = Cli::parse();
-    let options = CommonOptions::new(cli.format, cli.debug);
-    options.setup_debug();
-    
-    let mut manager = match create_connection_manager(&cli.url).await {
-        Ok(manager) => manager,
-        Err(e) => handle_error(e, "Failed to create Redis connection manager"),
-    };
+// Sorted set operations
+/// JSON has no infinity, so infinite scores are kept as Redis spells them
+fn score_to_json(score: f64) -> serde_json::Value {
+    if score.is_infinite() {
+        json!(if score > 0.0 { "inf" } else { "-inf" })
+    } else {
+        json!(score)
+    }
+}
+
+fn scored_members_to_json(members: Vec<(String, f64)>) -> Vec<serde_json::Value> {
+    members.into_iter()
+        .map(|(member, score)| json!({ "member": member, "score": score_to_json(score) }))
+        .collect()
+}
+
+async fn handle_zset_add(
+    manager: &mut ConnectionManager,
+    key: String,
+    members: Vec<String>,
+    add: ZAddOptions,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    if members.len() % 2 != 0 {
+        return Err(anyhow::anyhow!("Members must be given as score/member pairs, got {} arguments", members.len()));
+    }
+    for pair in members.chunks(2) {
+        pair[0].parse::<f64>()
+            .map_err(|_| anyhow::anyhow!("Invalid score '{}' for member '{}'", pair[0], pair[1]))?;
+    }
     
-    let result = match cli.command {
-        Commands::Set { key, value, expire } => {
-            handle_set_command(&mut manager, key, value, expire, &options).await
-        }
-        Commands::Get { key } => {
-            handle_get_command(&mut manager, key, &options).await
+    let mut cmd = redis::cmd("ZADD");
+    cmd.arg(&key);
+    for (flag, enabled) in [("NX", add.nx), ("XX", add.xx), ("GT", add.gt), ("LT", add.lt), ("CH", add.ch)] {
+        if enabled {
+            cmd.arg(flag);
+        }
+    }
+    cmd.arg(&members);
+    
+    let result: RedisResult<i64> = cmd.query_async(manager).await;
+    
+    match result {
+        Ok(count) => {
+            let response = json!({
+                "key": key,
+                "members_requested": members.len() / 2,
+                (if add.ch { "members_changed" } else { "members_added" }): count
+            });
+            println!("{}", format_output(&response, options.format));
+        }
+        Err(e) => return Err(anyhow::anyhow!("Failed to add members to sorted set '{}': {}", key, e)),
+    }
+    
+    Ok(())
+}
+
+async fn handle_zset_range(
+    manager: &mut ConnectionManager,
+    key: String,
+    start: String,
+    stop: String,
+    range: ZRangeOptions,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    if range.limit.is_some() && !range.by_score && !range.by_lex {
+        return Err(anyhow::anyhow!("--limit requires --by-score or --by-lex"));
+    }
+    
+    let mut cmd = redis::cmd("ZRANGE");
+    cmd.arg(&key).arg(&start).arg(&stop);
+    if range.by_score {
+        cmd.arg("BYSCORE");
+    } else if range.by_lex {
+        cmd.arg("BYLEX");
+    }
+    if range.rev {
+        cmd.arg("REV");
+    }
+    if let Some(limit) = &range.limit {
+        cmd.arg("LIMIT").arg(limit[0]).arg(limit[1]);
+    }
+    
+    let members = if range.withscores {
+        cmd.arg("WITHSCORES");
+        let result: RedisResult<Vec<(String, f64)>> = cmd.query_async(manager).await;
+        result.map(scored_members_to_json)
+    } else {
+        let result: RedisResult<Vec<String>> = cmd.query_async(manager).await;
+        result.map(|members| members.into_iter().map(|member| json!(member)).collect())
+    };
+    
+    match members {
+        Ok(members) => {
+            let response = json!({
+                "key": key,
+                "by": if range.by_score { "score" } else if range.by_lex { "lex" } else { "rank" },
+                "start": start,
+                "stop": stop,
+                "count": members.len(),
+                "members": members
+            });
+            println!("{}", format_output(&response, options.format));
+        }
+        Err(e) => return Err(anyhow::anyhow!("Failed to get range from sorted set '{}': {}", key, e)),
+    }
+    
+    Ok(())
+}
+
+async fn handle_zset_pop(
+    manager: &mut ConnectionManager,
+    key: String,
+    count: usize,
+    max: bool,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let result: RedisResult<Vec<(String, f64)>> = redis::cmd(if max { "ZPOPMAX" } else { "ZPOPMIN" })
+        .arg(&key)
+        .arg(count)
+        .query_async(manager)
+        .await;
+    
+    match result {
+        Ok(members) => {
+            let response = json!({
+                "key": key,
+                "operation": if max { "pop_max" } else { "pop_min" },
+                "count": members.len(),
+                "members": scored_members_to_json(members)
+            });
+            println!("{}", format_output(&response, options.format));
+        }
+        Err(e) => return Err(anyhow::anyhow!("Failed to pop from sorted set '{}': {}", key, e)),
+    }
+    
+    Ok(())
+}
+
+// Stream operations
+fn redis_value_to_json(value: &redis::Value) -> serde_json::Value {
+    match value {
+        redis::Value::Nil => serde_json::Value::Null,
+        redis::Value::Int(i) => json!(i),
+        redis::Value::Data(bytes) => json!(String::from_utf8_lossy(bytes)),
+        redis::Value::Bulk(items) => items.iter().map(redis_value_to_json).collect(),
+        redis::Value::Status(status) => json!(status),
+        redis::Value::Okay => json!("OK"),
+    }
+}
+
+fn redis_value_to_string(value: &redis::Value) -> String {
+    match redis_value_to_json(value) {
+        serde_json::Value::String(s) => s,
+        other => other.to_string(),
+    }
+}
+
+/// Convert an XRANGE-style reply (`[[id, [field, value, ...]], ...]`) to JSON entries
+fn stream_entries_to_json(value: &redis::Value) -> Vec<serde_json::Value> {
+    let redis::Value::Bulk(entries) = value else {
+        return Vec::new();
+    };
+    entries.iter().filter_map(|entry| {
+        let redis::Value::Bulk(parts) = entry else {
+            return None;
+        };
+        let id = redis_value_to_string(parts.first()?);
+        // Entries deleted while pending come back with nil fields
+        let fields = match parts.get(1) {
+            Some(redis::Value::Bulk(fields)) => {
+                let map: serde_json::Map<String, serde_json::Value> = fields.chunks(2)
+                    .map(|pair| (redis_value_to_string(&pair[0]), pair.get(1).map(redis_value_to_json).unwrap_or_default()))
+                    .collect();
+                serde_json::Value::Object(map)
+            }
+            _ => serde_json::Value::Null,
+        };
+        Some(json!({ "id": id, "fields": fields }))
+    }).collect()
+}
+
+/// Convert an XREAD/XREADGROUP reply (`[[key, entries], ...]`, nil on timeout) to JSON
+fn stream_read_to_json(value: &redis::Value) -> Vec<serde_json::Value> {
+    let redis::Value::Bulk(streams) = value else {
+        return Vec::new();
+    };
+    streams.iter().filter_map(|stream| {
+        let redis::Value::Bulk(parts) = stream else {
+            return None;
+        };
+        let entries = stream_entries_to_json(parts.get(1)?);
+        Some(json!({
+            "stream": redis_value_to_string(parts.first()?),
+            "count": entries.len(),
+            "entries": entries
+        }))
+    }).collect()
+}
+
+async fn handle_stream_add(
+    manager: &mut ConnectionManager,
+    key: String,
+    fields: Vec<String>,
+    id: String,
+    maxlen: Option<u64>,
+    approximate: bool,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    if fields.len() % 2 != 0 {
+        return Err(anyhow::anyhow!("Fields must be given as field/value pairs, got {} arguments", fields.len()));
+    }
+    
+    let mut cmd = redis::cmd("XADD");
+    cmd.arg(&key);
+    if let Some(maxlen) = maxlen {
+        cmd.arg("MAXLEN").arg(if approximate { "~" } else { "=" }).arg(maxlen);
+    }
+    cmd.arg(&id).arg(&fields);
+    
+    let result: RedisResult<String> = cmd.query_async(manager).await;
+    
+    match result {
+        Ok(entry_id) => {
+            let response = json!({
+                "key": key,
+                "id": entry_id,
+                "fields": fields.len() / 2
+            });
+            println!("{}", format_output(&response, options.format));
+        }
+        Err(e) => return Err(anyhow::anyhow!("Failed to add entry to stream '{}': {}", key, e)),
+    }
+    
+    Ok(())
+}
+
+async fn handle_stream_range(
+    manager: &mut ConnectionManager,
+    key: String,
+    start: String,
+    end: String,
+    count: Option<usize>,
+    reverse: bool,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    // XREVRANGE takes the bounds in end, start order
+    let mut cmd = if reverse {
+        let mut cmd = redis::cmd("XREVRANGE");
+        cmd.arg(&key).arg(&end).arg(&start);
+        cmd
+    } else {
+        let mut cmd = redis::cmd("XRANGE");
+        cmd.arg(&key).arg(&start).arg(&end);
+        cmd
+    };
+    if let Some(count) = count {
+        cmd.arg("COUNT").arg(count);
+    }
+    
+    let result: RedisResult<redis::Value> = cmd.query_async(manager).await;
+    
+    match result {
+        Ok(reply) => {
+            let entries = stream_entries_to_json(&reply);
+            let response = json!({
+                "key": key,
+                "start": start,
+                "end": end,
+                "count": entries.len(),
+                "entries": entries
+            });
+            println!("{}", format_output(&response, options.format));
+        }
+        Err(e) => return Err(anyhow::anyhow!("Failed to get range from stream '{}': {}", key, e)),
+    }
+    
+    Ok(())
+}
+
+async fn handle_stream_read(
+    manager: &mut ConnectionManager,
+    keys: Vec<String>,
+    id: Option<String>,
+    read: StreamReadOptions,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let id = id.unwrap_or_else(|| "0".to_string());
+    let mut cmd = redis::cmd("XREAD");
+    if let Some(count) = read.count {
+        cmd.arg("COUNT").arg(count);
+    }
+    if let Some(block) = read.block {
+        cmd.arg("BLOCK").arg(block);
+    }
+    cmd.arg("STREAMS").arg(&keys);
+    for _ in &keys {
+        cmd.arg(&id);
+    }
+    
+    let result: RedisResult<redis::Value> = cmd.query_async(manager).await;
+    
+    match result {
+        Ok(reply) => {
+            let streams = stream_read_to_json(&reply);
+            let response = json!({
+                "id": id,
+                "timed_out": reply == redis::Value::Nil && read.block.is_some(),
+                "streams": streams
+            });
+            println!("{}", format_output(&response, options.format));
+        }
+        Err(e) => return Err(anyhow::anyhow!("Failed to read from streams: {}", e)),
+    }
+    
+    Ok(())
+}
+
+/// Keep issuing blocking XREADs and print every entry as one NDJSON line
+async fn handle_stream_follow(
+    manager: &mut ConnectionManager,
+    keys: Vec<String>,
+    id: Option<String>,
+    read: StreamReadOptions,
+) -> Result<(), anyhow::Error> {
+    let mut last_ids = Vec::with_capacity(keys.len());
+    for key in &keys {
+        let id = id.clone().unwrap_or_else(|| "$".to_string());
+        // Pin "$" to the current last entry so nothing added between two reads is missed
+        let id = if id == "$" {
+            let reply: redis::Value = redis::cmd("XREVRANGE").arg(key).arg("+").arg("-").arg("COUNT").arg(1)
+                .query_async(manager).await
+                .map_err(|e| anyhow::anyhow!("Failed to read stream '{}': {}", key, e))?;
+            stream_entries_to_json(&reply).first()
+                .and_then(|entry| entry["id"].as_str().map(str::to_string))
+                .unwrap_or_else(|| "0-0".to_string())
+        } else {
+            id
+        };
+        last_ids.push(id);
+    }
+    eprintln!("Following {}", keys.join(", "));
+    
+    // Pinned once so an interrupt between two reads isn't dropped
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    
+    loop {
+        let mut cmd = redis::cmd("XREAD");
+        if let Some(count) = read.count {
+            cmd.arg("COUNT").arg(count);
+        }
+        cmd.arg("BLOCK").arg(read.block.unwrap_or(5000)).arg("STREAMS").arg(&keys).arg(&last_ids);
+        
+        let reply: redis::Value = tokio::select! {
+            reply = cmd.query_async(manager) => {
+                reply.map_err(|e| anyhow::anyhow!("Failed to read from streams: {}", e))?
+            },
+            _ = &mut ctrl_c => break,
+        };
+        
+        for stream in stream_read_to_json(&reply) {
+            let key = stream["stream"].as_str().unwrap_or_default().to_string();
+            let Some(index) = keys.iter().position(|k| *k == key) else {
+                continue;
+            };
+            for entry in stream["entries"].as_array().into_iter().flatten() {
+                last_ids[index] = entry["id"].as_str().unwrap_or_default().to_string();
+                let event = json!({
+                    "stream": key,
+                    "id": entry["id"],
+                    "fields": entry["fields"],
+                    "received_at": chrono::Utc::now().to_rfc3339(),
+                });
+                println!("{}", event);
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+async fn handle_stream_group_create(
+    manager: &mut ConnectionManager,
+    key: String,
+    group: String,
+    id: String,
+    mkstream: bool,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let mut cmd = redis::cmd("XGROUP");
+    cmd.arg("CREATE").arg(&key).arg(&group).arg(&id);
+    if mkstream {
+        cmd.arg("MKSTREAM");
+    }
+    
+    let result: RedisResult<String> = cmd.query_async(manager).await;
+    
+    match result {
+        Ok(_) => {
+            let response = json!({
+                "status": "ok",
+                "key": key,
+                "group": group,
+                "id": id
+            });
+            println!("{}", format_output(&response, options.format));
+        }
+        Err(e) => return Err(anyhow::anyhow!("Failed to create group '{}' on stream '{}': {}", group, key, e)),
+    }
+    
+    Ok(())
+}
+
+async fn handle_stream_read_group(
+    manager: &mut ConnectionManager,
+    member: GroupConsumer,
+    keys: Vec<String>,
+    id: String,
+    read: StreamReadOptions,
+    noack: bool,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let GroupConsumer { group, consumer } = member;
+    let mut cmd = redis::cmd("XREADGROUP");
+    cmd.arg("GROUP").arg(&group).arg(&consumer);
+    if let Some(count) = read.count {
+        cmd.arg("COUNT").arg(count);
+    }
+    if let Some(block) = read.block {
+        cmd.arg("BLOCK").arg(block);
+    }
+    if noack {
+        cmd.arg("NOACK");
+    }
+    cmd.arg("STREAMS").arg(&keys);
+    for _ in &keys {
+        cmd.arg(&id);
+    }
+    
+    let result: RedisResult<redis::Value> = cmd.query_async(manager).await;
+    
+    match result {
+        Ok(reply) => {
+            let streams = stream_read_to_json(&reply);
+            let response = json!({
+                "group": group,
+                "consumer": consumer,
+                "id": id,
+                "timed_out": reply == redis::Value::Nil && read.block.is_some(),
+                "streams": streams
+            });
+            println!("{}", format_output(&response, options.format));
+        }
+        Err(e) => return Err(anyhow::anyhow!("Failed to read from group '{}': {}", group, e)),
+    }
+    
+    Ok(())
+}
+
+async fn handle_stream_ack(
+    manager: &mut ConnectionManager,
+    key: String,
+    group: String,
+    ids: Vec<String>,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let result: RedisResult<i64> = redis::cmd("XACK").arg(&key).arg(&group).arg(&ids).query_async(manager).await;
+    
+    match result {
+        Ok(acknowledged) => {
+            let response = json!({
+                "key": key,
+                "group": group,
+                "acknowledged": acknowledged,
+                "requested": ids.len()
+            });
+            println!("{}", format_output(&response, options.format));
+        }
+        Err(e) => return Err(anyhow::anyhow!("Failed to acknowledge entries in stream '{}': {}", key, e)),
+    }
+    
+    Ok(())
+}
+
+async fn handle_stream_pending(
+    manager: &mut ConnectionManager,
+    key: String,
+    group: String,
+    count: Option<usize>,
+    consumer: Option<String>,
+    idle: Option<u64>,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let mut cmd = redis::cmd("XPENDING");
+    cmd.arg(&key).arg(&group);
+    if let Some(count) = count {
+        if let Some(idle) = idle {
+            cmd.arg("IDLE").arg(idle);
+        }
+        cmd.arg("-").arg("+").arg(count);
+        if let Some(consumer) = &consumer {
+            cmd.arg(consumer);
+        }
+    }
+    
+    let reply: redis::Value = cmd.query_async(manager).await
+        .map_err(|e| anyhow::anyhow!("Failed to get pending entries of group '{}': {}", group, e))?;
+    let reply = redis_value_to_json(&reply);
+    
+    let response = if count.is_some() {
+        // Extended form: [[id, consumer, idle_ms, deliveries], ...]
+        let entries: Vec<serde_json::Value> = reply.as_array().into_iter().flatten()
+            .map(|entry| json!({
+                "id": entry[0],
+                "consumer": entry[1],
+                "idle_ms": entry[2],
+                "deliveries": entry[3]
+            }))
+            .collect();
+        json!({
+            "key": key,
+            "group": group,
+            "count": entries.len(),
+            "entries": entries
+        })
+    } else {
+        // Summary form: [count, smallest_id, greatest_id, [[consumer, count], ...]]
+        let consumers: Vec<serde_json::Value> = reply[3].as_array().into_iter().flatten()
+            .map(|pair| json!({
+                "consumer": pair[0],
+                "pending": pair[1].as_str().and_then(|n| n.parse::<i64>().ok())
+            }))
+            .collect();
+        json!({
+            "key": key,
+            "group": group,
+            "pending": reply[0],
+            "smallest_id": reply[1],
+            "greatest_id": reply[2],
+            "consumers": consumers
+        })
+    };
+    println!("{}", format_output(&response, options.format));
+    
+    Ok(())
+}
+
+async fn handle_stream_trim(
+    manager: &mut ConnectionManager,
+    key: String,
+    maxlen: Option<u64>,
+    minid: Option<String>,
+    approximate: bool,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let mut cmd = redis::cmd("XTRIM");
+    cmd.arg(&key);
+    let threshold = if approximate { "~" } else { "=" };
+    match (maxlen, &minid) {
+        (Some(maxlen), _) => cmd.arg("MAXLEN").arg(threshold).arg(maxlen),
+        (None, Some(minid)) => cmd.arg("MINID").arg(threshold).arg(minid),
+        (None, None) => return Err(anyhow::anyhow!("Either --maxlen or --minid is required")),
+    };
+    
+    let result: RedisResult<i64> = cmd.query_async(manager).await;
+    
+    match result {
+        Ok(removed) => {
+            let response = json!({
+                "key": key,
+                "maxlen": maxlen,
+                "minid": minid,
+                "removed": removed
+            });
+            println!("{}", format_output(&response, options.format));
+        }
+        Err(e) => return Err(anyhow::anyhow!("Failed to trim stream '{}': {}", key, e)),
+    }
+    
+    Ok(())
+}
+
+// Pub/sub operations
+async fn handle_publish_command(
+    manager: &mut ConnectionManager,
+    channel: String,
+    message: String,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let result: RedisResult<i64> = manager.publish(&channel, &message).await;
+    
+    match result {
+        Ok(receivers) => {
+            let response = json!({
+                "channel": channel,
+                "receivers": receivers
+            });
+            println!("{}", format_output(&response, options.format));
+        }
+        Err(e) => return Err(anyhow::anyhow!("Failed to publish to channel '{}': {}", channel, e)),
+    }
+    
+    Ok(())
+}
+
+/// Subscribe on a dedicated connection (ConnectionManager cannot enter
+/// subscriber mode) and print every message as one NDJSON line
+async fn handle_subscribe_command(
+    url: &str,
+    channels: Vec<String>,
+    count: Option<usize>,
+    timeout: Option<u64>,
+) -> Result<(), anyhow::Error> {
+    use futures::StreamExt;
+    
+    let client = Client::open(url)?;
+    let mut pubsub = client.get_async_pubsub().await
+        .map_err(|e| anyhow::anyhow!("Failed to open pub/sub connection: {}", e))?;
+    
+    for channel in &channels {
+        let subscribed = if channel.contains(['*', '?', '[']) {
+            pubsub.psubscribe(channel).await
+        } else {
+            pubsub.subscribe(channel).await
+        };
+        subscribed.map_err(|e| anyhow::anyhow!("Failed to subscribe to '{}': {}", channel, e))?;
+    }
+    eprintln!("Subscribed to {}", channels.join(", "));
+    
+    let deadline = async {
+        match timeout {
+            Some(seconds) => tokio::time::sleep(std::time::Duration::from_secs(seconds)).await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::pin!(deadline);
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    
+    let mut messages = pubsub.on_message();
+    let mut received = 0;
+    while count.is_none_or(|count| received < count) {
+        tokio::select! {
+            message = messages.next() => {
+                let Some(message) = message else {
+                    return Err(anyhow::anyhow!("Pub/sub connection closed"));
+                };
+                received += 1;
+                let payload: Vec<u8> = message.get_payload()?;
+                let pattern: Option<String> = if message.from_pattern() {
+                    message.get_pattern()?
+                } else {
+                    None
+                };
+                let event = json!({
+                    "channel": message.get_channel_name(),
+                    "pattern": pattern,
+                    "payload": String::from_utf8_lossy(&payload),
+                    "received_at": chrono::Utc::now().to_rfc3339(),
+                });
+                println!("{}", event);
+            },
+            _ = &mut deadline => break,
+            _ = &mut ctrl_c => break,
+        }
+    }
+    
+    Ok(())
+}
+
+// Scripting and transactions
+async fn handle_eval_command(
+    manager: &mut ConnectionManager,
+    script: String,
+    keys: Vec<String>,
+    args: Vec<String>,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let code = match script.strip_prefix('@') {
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read script '{}': {}", path, e))?,
+        None => script,
+    };
+    let sha = redis::Script::new(&code).get_hash().to_string();
+    
+    let mut evalsha = redis::cmd("EVALSHA");
+    evalsha.arg(&sha).arg(keys.len()).arg(&keys).arg(&args);
+    
+    // Only send the script body when the server does not have it cached yet
+    let (result, loaded) = match evalsha.query_async::<_, redis::Value>(manager).await {
+        Err(e) if e.kind() == redis::ErrorKind::NoScriptError => {
+            let _: String = redis::cmd("SCRIPT").arg("LOAD").arg(&code).query_async(manager).await
+                .map_err(|e| anyhow::anyhow!("Failed to load script: {}", e))?;
+            (evalsha.query_async::<_, redis::Value>(manager).await, true)
+        }
+        other => (other, false),
+    };
+    
+    match result {
+        Ok(reply) => {
+            let response = json!({
+                "sha1": sha,
+                "loaded": loaded,
+                "keys": keys,
+                "result": redis_value_to_json(&reply)
+            });
+            println!("{}", format_output(&response, options.format));
+        }
+        Err(e) => return Err(anyhow::anyhow!("Script {} failed: {}", sha, e)),
+    }
+    
+    Ok(())
+}
+
+/// Turn a JSON command (`["SET", "key", 1]`) into its arguments
+fn json_command_args(command: &serde_json::Value) -> Result<Vec<String>, anyhow::Error> {
+    let parts = command.as_array()
+        .filter(|parts| !parts.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("Each command must be a non-empty array, got {}", command))?;
+    Ok(parts.iter().map(|part| match part {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }).collect())
+}
+
+/// Read a transaction file: either a list of commands or an object with
+/// `watch` keys and `commands`. Returns the watched keys and the commands
+fn parse_tx_document(content: &str) -> Result<(Vec<String>, Vec<Vec<String>>), anyhow::Error> {
+    let document: serde_json::Value = serde_json::from_str(content)?;
+    
+    let mut watch = Vec::new();
+    let commands = match &document {
+        serde_json::Value::Object(object) => {
+            for key in object.get("watch").and_then(|w| w.as_array()).into_iter().flatten() {
+                watch.push(key.as_str().map(str::to_string).unwrap_or_else(|| key.to_string()));
+            }
+            object.get("commands").cloned().unwrap_or_default()
+        }
+        other => other.clone(),
+    };
+    let commands: Vec<Vec<String>> = commands.as_array()
+        .ok_or_else(|| anyhow::anyhow!("Transaction file must contain a list of commands"))?
+        .iter()
+        .map(json_command_args)
+        .collect::<Result<_, _>>()?;
+    if commands.is_empty() {
+        return Err(anyhow::anyhow!("Transaction file contains no commands"));
+    }
+    
+    Ok((watch, commands))
+}
+
+/// A RESP2 reply as sent by the server. Unlike `redis::Value` it keeps error
+/// replies nested in an array, which is how EXEC reports a failed command
+#[derive(Debug, PartialEq)]
+enum RawReply {
+    Nil,
+    Int(i64),
+    Data(Vec<u8>),
+    Status(String),
+    Error(String),
+    Array(Vec<RawReply>),
+}
+
+impl RawReply {
+    fn to_json(&self) -> serde_json::Value {
+        match self {
+            RawReply::Nil => serde_json::Value::Null,
+            RawReply::Int(i) => json!(i),
+            RawReply::Data(bytes) => json!(String::from_utf8_lossy(bytes)),
+            RawReply::Status(status) => json!(status),
+            RawReply::Error(message) => json!({ "error": message }),
+            RawReply::Array(items) => items.iter().map(RawReply::to_json).collect(),
+        }
+    }
+}
+
+/// Parse one reply from the start of `buf`, returning it with the number of
+/// bytes it took, or None if `buf` doesn't hold a whole reply yet
+fn parse_raw_reply(buf: &[u8]) -> Result<Option<(RawReply, usize)>, anyhow::Error> {
+    let Some(line_end) = buf.windows(2).position(|w| w == b"\r\n") else {
+        return Ok(None);
+    };
+    let line = String::from_utf8_lossy(&buf[1..line_end]);
+    let length = || line.parse::<i64>()
+        .map_err(|_| anyhow::anyhow!("Invalid length in reply: {}", line));
+    let mut used = line_end + 2;
+    
+    let reply = match buf[0] {
+        b'+' => RawReply::Status(line.to_string()),
+        b'-' => RawReply::Error(line.to_string()),
+        b':' => RawReply::Int(length()?),
+        b'$' => match usize::try_from(length()?) {
+            Ok(len) => {
+                if buf.len() < used + len + 2 {
+                    return Ok(None);
+                }
+                let data = buf[used..used + len].to_vec();
+                used += len + 2;
+                RawReply::Data(data)
+            }
+            Err(_) => RawReply::Nil,
+        },
+        b'*' => match usize::try_from(length()?) {
+            Ok(len) => {
+                let mut items = Vec::new();
+                for _ in 0..len {
+                    let Some((item, item_len)) = parse_raw_reply(&buf[used..])? else {
+                        return Ok(None);
+                    };
+                    items.push(item);
+                    used += item_len;
+                }
+                RawReply::Array(items)
+            }
+            Err(_) => RawReply::Nil,
+        },
+        other => return Err(anyhow::anyhow!("Unexpected reply type '{}'", other as char)),
+    };
+    Ok(Some((reply, used)))
+}
+
+trait RawStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send {}
+impl<T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send> RawStream for T {}
+
+/// A plain connection for commands whose replies redis-rs can't represent
+struct RawConnection {
+    stream: Box<dyn RawStream>,
+    buffer: Vec<u8>,
+}
+
+impl RawConnection {
+    async fn connect(url: &str) -> Result<Self, anyhow::Error> {
+        use redis::IntoConnectionInfo;
+        
+        let info = url.into_connection_info()?;
+        let stream: Box<dyn RawStream> = match &info.addr {
+            redis::ConnectionAddr::Tcp(host, port) => Box::new(tokio::net::TcpStream::connect((host.as_str(), *port)).await?),
+            redis::ConnectionAddr::Unix(path) => Box::new(tokio::net::UnixStream::connect(path).await?),
+            redis::ConnectionAddr::TcpTls { .. } => return Err(anyhow::anyhow!("TLS connections are not supported")),
+        };
+        let mut connection = RawConnection { stream, buffer: Vec::new() };
+        
+        if let Some(password) = &info.redis.password {
+            let mut auth = redis::cmd("AUTH");
+            if let Some(username) = &info.redis.username {
+                auth.arg(username);
+            }
+            auth.arg(password);
+            connection.query(&auth).await
+                .map_err(|e| anyhow::anyhow!("Authentication failed: {}", e))?;
+        }
+        if info.redis.db != 0 {
+            connection.query(redis::cmd("SELECT").arg(info.redis.db)).await?;
+        }
+        
+        Ok(connection)
+    }
+    
+    async fn send(&mut self, commands: &[&redis::Cmd]) -> Result<(), anyhow::Error> {
+        use tokio::io::AsyncWriteExt;
+        
+        let packed: Vec<u8> = commands.iter().flat_map(|cmd| cmd.get_packed_command()).collect();
+        self.stream.write_all(&packed).await?;
+        Ok(())
+    }
+    
+    async fn read_reply(&mut self) -> Result<RawReply, anyhow::Error> {
+        use tokio::io::AsyncReadExt;
+        
+        loop {
+            if let Some((reply, used)) = parse_raw_reply(&self.buffer)? {
+                self.buffer.drain(..used);
+                return Ok(reply);
+            }
+            let mut chunk = [0u8; 8192];
+            let read = self.stream.read(&mut chunk).await?;
+            if read == 0 {
+                return Err(anyhow::anyhow!("Connection closed by the server"));
+            }
+            self.buffer.extend_from_slice(&chunk[..read]);
+        }
+    }
+    
+    /// Send one command and fail on an error reply
+    async fn query(&mut self, cmd: &redis::Cmd) -> Result<RawReply, anyhow::Error> {
+        self.send(&[cmd]).await?;
+        match self.read_reply().await? {
+            RawReply::Error(message) => Err(anyhow::anyhow!(message)),
+            reply => Ok(reply),
+        }
+    }
+}
+
+/// Run the commands in MULTI/EXEC on a plain connection, so that a command
+/// failing inside EXEC is reported by index next to the replies of the others
+async fn handle_tx_command(
+    url: &str,
+    file: String,
+    mut watch: Vec<String>,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let content = std::fs::read_to_string(&file)
+        .map_err(|e| anyhow::anyhow!("Failed to read transaction file '{}': {}", file, e))?;
+    let (file_watch, commands) = parse_tx_document(&content)
+        .map_err(|e| anyhow::anyhow!("Invalid transaction file '{}': {}", file, e))?;
+    watch.extend(file_watch);
+    
+    let mut connection = RawConnection::connect(url).await
+        .map_err(|e| anyhow::anyhow!("Failed to connect: {}", e))?;
+    
+    if !watch.is_empty() {
+        connection.query(redis::cmd("WATCH").arg(&watch)).await
+            .map_err(|e| anyhow::anyhow!("Failed to watch keys: {}", e))?;
+    }
+    
+    let multi = redis::cmd("MULTI");
+    let exec = redis::cmd("EXEC");
+    let queued: Vec<redis::Cmd> = commands.iter()
+        .map(|command| {
+            let mut cmd = redis::cmd(&command[0]);
+            cmd.arg(&command[1..]);
+            cmd
+        })
+        .collect();
+    let mut batch = vec![&multi];
+    batch.extend(&queued);
+    batch.push(&exec);
+    connection.send(&batch).await?;
+    
+    if let RawReply::Error(message) = connection.read_reply().await? {
+        return Err(anyhow::anyhow!("MULTI failed: {}", message));
+    }
+    // Commands rejected while queueing (unknown command, wrong arity) make EXEC discard everything
+    let mut queue_errors = Vec::new();
+    for _ in &commands {
+        queue_errors.push(match connection.read_reply().await? {
+            RawReply::Error(message) => Some(message),
+            _ => None,
+        });
+    }
+    let exec_reply = connection.read_reply().await?;
+    
+    let entry = |index: usize, command: &[String]| json!({ "index": index, "command": command[0].to_uppercase() });
+    match exec_reply {
+        RawReply::Array(replies) => {
+            let mut failed = 0;
+            let replies: Vec<serde_json::Value> = commands.iter().zip(&replies).enumerate()
+                .map(|(index, (command, reply))| {
+                    let mut result = entry(index, command);
+                    match reply {
+                        RawReply::Error(message) => {
+                            failed += 1;
+                            result["error"] = json!(message);
+                        }
+                        reply => result["reply"] = reply.to_json(),
+                    }
+                    result
+                })
+                .collect();
+            let response = json!({
+                "status": "committed",
+                "watched": watch,
+                "commands": commands.len(),
+                "failed": failed,
+                "replies": replies
+            });
+            println!("{}", format_output(&response, options.format));
+            
+            // Redis runs the remaining commands when one fails inside EXEC
+            if failed > 0 {
+                return Err(anyhow::anyhow!("{} command(s) failed, the others were applied", failed));
+            }
+        }
+        // EXEC replies nil when a watched key was modified before it ran
+        RawReply::Nil => {
+            let response = json!({
+                "status": "aborted",
+                "watched": watch,
+                "commands": commands.len(),
+                "replies": null
+            });
+            println!("{}", format_output(&response, options.format));
+            return Err(anyhow::anyhow!("Transaction aborted: a watched key was modified"));
+        }
+        RawReply::Error(message) => {
+            let errors: Vec<serde_json::Value> = commands.iter().zip(queue_errors).enumerate()
+                .filter_map(|(index, (command, error))| {
+                    let mut result = entry(index, command);
+                    result["error"] = json!(error?);
+                    Some(result)
+                })
+                .collect();
+            let response = json!({
+                "status": "discarded",
+                "watched": watch,
+                "commands": commands.len(),
+                "errors": errors
+            });
+            println!("{}", format_output(&response, options.format));
+            return Err(anyhow::anyhow!("Transaction discarded, nothing was executed: {}", message));
+        }
+        other => return Err(anyhow::anyhow!("Unexpected EXEC reply: {}", other.to_json())),
+    }
+    
+    Ok(())
+}
+
+// Export and import
+/// The command that reads a whole value of `key_type`
+fn read_command(key: &str, key_type: &str) -> Option<redis::Cmd> {
+    let mut cmd = match key_type {
+        "string" => redis::cmd("GET"),
+        "list" => redis::cmd("LRANGE"),
+        "set" => redis::cmd("SMEMBERS"),
+        "zset" => redis::cmd("ZRANGE"),
+        "hash" => redis::cmd("HGETALL"),
+        "stream" => redis::cmd("XRANGE"),
+        _ => return None,
+    };
+    cmd.arg(key);
+    match key_type {
+        "list" => cmd.arg(0).arg(-1),
+        "zset" => cmd.arg(0).arg(-1).arg("WITHSCORES"),
+        "stream" => cmd.arg("-").arg("+"),
+        _ => &mut cmd,
+    };
+    Some(cmd)
+}
+
+fn redis_value_is_utf8(value: &redis::Value) -> bool {
+    match value {
+        redis::Value::Data(bytes) => std::str::from_utf8(bytes).is_ok(),
+        redis::Value::Bulk(items) => items.iter().all(redis_value_is_utf8),
+        _ => true,
+    }
+}
+
+/// Convert the reply of the read command for `key_type` to its export representation
+fn export_value_to_json(key_type: &str, value: &redis::Value) -> serde_json::Value {
+    let items = match value {
+        redis::Value::Bulk(items) => items.as_slice(),
+        _ => &[],
+    };
+    match key_type {
+        "hash" => {
+            let map: serde_json::Map<String, serde_json::Value> = items.chunks(2)
+                .map(|pair| (redis_value_to_string(&pair[0]), pair.get(1).map(redis_value_to_json).unwrap_or_default()))
+                .collect();
+            serde_json::Value::Object(map)
+        }
+        "zset" => {
+            let members: Vec<serde_json::Value> = items.chunks(2)
+                .map(|pair| json!({
+                    "member": redis_value_to_json(&pair[0]),
+                    "score": pair.get(1)
+                        .and_then(|score| redis_value_to_string(score).parse::<f64>().ok())
+                        .map(score_to_json)
+                }))
+                .collect();
+            json!(members)
+        }
+        "set" => {
+            let mut members: Vec<String> = items.iter().map(redis_value_to_string).collect();
+            members.sort();
+            json!(members)
+        }
+        "stream" => json!(stream_entries_to_json(value)),
+        _ => redis_value_to_json(value),
+    }
+}
+
+/// Queue the commands that recreate a key from its exported value
+fn add_import_commands(
+    pipe: &mut redis::Pipeline,
+    key: &str,
+    key_type: &str,
+    value: &serde_json::Value,
+) -> Result<(), anyhow::Error> {
+    let text = |v: &serde_json::Value| match v {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    let items = || value.as_array().into_iter().flatten();
+    
+    // Redis has no empty collections, so there would be nothing to create
+    let empty = match key_type {
+        "hash" => value.as_object().is_some_and(|fields| fields.is_empty()),
+        "list" | "set" | "zset" | "stream" => items().next().is_none(),
+        _ => false,
+    };
+    if empty {
+        return Err(anyhow::anyhow!("Cannot import an empty {}", key_type));
+    }
+    
+    match key_type {
+        "string" => {
+            pipe.cmd("SET").arg(key).arg(text(value)).ignore();
+        }
+        "list" => {
+            pipe.cmd("RPUSH").arg(key).arg(items().map(text).collect::<Vec<_>>()).ignore();
+        }
+        "set" => {
+            pipe.cmd("SADD").arg(key).arg(items().map(text).collect::<Vec<_>>()).ignore();
+        }
+        "hash" => {
+            let fields = value.as_object()
+                .ok_or_else(|| anyhow::anyhow!("Hash value must be an object"))?;
+            let cmd = pipe.cmd("HSET").arg(key);
+            for (field, field_value) in fields {
+                cmd.arg(field).arg(text(field_value));
+            }
+            cmd.ignore();
+        }
+        "zset" => {
+            let cmd = pipe.cmd("ZADD").arg(key);
+            for member in items() {
+                let score = match &member["score"] {
+                    serde_json::Value::String(score) => score.parse::<f64>().ok().filter(|score| !score.is_nan()),
+                    score => score.as_f64(),
+                };
+                let score = score
+                    .ok_or_else(|| anyhow::anyhow!("Sorted set member without a numeric score: {}", member))?;
+                cmd.arg(score).arg(text(&member["member"]));
+            }
+            cmd.ignore();
+        }
+        "stream" => {
+            for entry in items() {
+                let cmd = pipe.cmd("XADD").arg(key).arg(text(&entry["id"]));
+                for (field, field_value) in entry["fields"].as_object().into_iter().flatten() {
+                    cmd.arg(field).arg(text(field_value));
+                }
+                cmd.ignore();
+            }
+        }
+        other => return Err(anyhow::anyhow!("Cannot import type '{}' without a dump payload", other)),
+    }
+    
+    Ok(())
+}
+
+/// Build the export record for a value read natively, or `None` when it isn't
+/// valid UTF-8 and has to be exported as a DUMP payload instead
+fn export_record(key: &str, key_type: &str, ttl_ms: Option<i64>, value: &redis::Value) -> Option<serde_json::Value> {
+    redis_value_is_utf8(value)
+        .then(|| json!({ "key": key, "type": key_type, "ttl_ms": ttl_ms, "value": export_value_to_json(key_type, value) }))
+}
+
+fn dump_record(key: &str, key_type: &str, ttl_ms: Option<i64>, payload: &[u8]) -> serde_json::Value {
+    use base64::prelude::*;
+    json!({ "key": key, "type": key_type, "ttl_ms": ttl_ms, "dump": BASE64_STANDARD.encode(payload) })
+}
+
+async fn handle_export_command(
+    manager: &mut ConnectionManager,
+    pattern: String,
+    output: Option<String>,
+    dump: bool,
+    count: usize,
+    key_type: Option<String>,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    use std::io::Write;
+    
+    let (keys, _) = scan_keys(manager, &pattern, count.max(1), key_type.as_deref(), None)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to scan keys with pattern '{}': {}", pattern, e))?;
+    
+    let mut writer: Box<dyn Write> = match &output {
+        Some(path) => Box::new(std::io::BufWriter::new(std::fs::File::create(path)
+            .map_err(|e| anyhow::anyhow!("Failed to create '{}': {}", path, e))?)),
+        None => Box::new(std::io::BufWriter::new(std::io::stdout().lock())),
+    };
+    
+    // XRANGE alone would drop a stream's consumer groups, pending entries and
+    // last generated ID, so streams are always dumped
+    let read_natively = |key_type: &str| !dump && key_type != "stream";
+    
+    let mut exported = 0;
+    let mut dumped = 0;
+    for chunk in keys.chunks(count.max(1)) {
+        let mut pipe = redis::pipe();
+        for key in chunk {
+            pipe.cmd("TYPE").arg(key).cmd("PTTL").arg(key);
+        }
+        let meta: Vec<(String, i64)> = pipe.query_async(manager).await
+            .map_err(|e| anyhow::anyhow!("Failed to read key types: {}", e))?;
+        
+        let mut pipe = redis::pipe();
+        for (key, (key_type, _)) in chunk.iter().zip(&meta) {
+            match read_command(key, key_type).filter(|_| read_natively(key_type)) {
+                Some(cmd) => pipe.add_command(cmd),
+                None => pipe.cmd("DUMP").arg(key),
+            };
+        }
+        let values: Vec<redis::Value> = pipe.query_async(manager).await
+            .map_err(|e| anyhow::anyhow!("Failed to read values: {}", e))?;
+        
+        for ((key, (key_type, pttl)), value) in chunk.iter().zip(meta).zip(values) {
+            // The key expired or was deleted since it was scanned. A PTTL of 0 means it
+            // expires within the millisecond, and import has no way to restore that
+            if key_type == "none" || value == redis::Value::Nil || pttl == 0 {
+                continue;
+            }
+            let ttl_ms = (pttl > 0).then_some(pttl);
+            
+            let native = read_natively(&key_type) && read_command(key, &key_type).is_some();
+            let record = match native.then(|| export_record(key, &key_type, ttl_ms, &value)).flatten() {
+                Some(record) => record,
+                None => {
+                    // Binary values and module types fall back to a DUMP payload
+                    let payload: Vec<u8> = if native {
+                        redis::cmd("DUMP").arg(key).query_async(manager).await?
+                    } else {
+                        redis::from_redis_value(&value)?
+                    };
+                    dumped += 1;
+                    dump_record(key, &key_type, ttl_ms, &payload)
+                }
+            };
+            writeln!(writer, "{}", record)?;
+            exported += 1;
+        }
+    }
+    writer.flush()?;
+    drop(writer);
+    
+    let response = json!({
+        "pattern": pattern,
+        "output": output,
+        "exported": exported,
+        "dumped": dumped
+    });
+    match output {
+        Some(_) => println!("{}", format_output(&response, options.format)),
+        None => eprintln!("{}", response),
+    }
+    
+    Ok(())
+}
+
+async fn handle_import_command(
+    manager: &mut ConnectionManager,
+    file: String,
+    replace: bool,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    use base64::prelude::*;
+    use std::io::BufRead;
+    
+    let reader: Box<dyn BufRead> = if file == "-" {
+        Box::new(std::io::stdin().lock())
+    } else {
+        Box::new(std::io::BufReader::new(std::fs::File::open(&file)
+            .map_err(|e| anyhow::anyhow!("Failed to open '{}': {}", file, e))?))
+    };
+    
+    let mut imported = 0;
+    let mut conflicts = Vec::new();
+    let mut failed = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: serde_json::Value = serde_json::from_str(&line)
+            .map_err(|e| anyhow::anyhow!("Invalid JSON on line {}: {}", index + 1, e))?;
+        let key = record["key"].as_str()
+            .ok_or_else(|| anyhow::anyhow!("Missing key on line {}", index + 1))?
+            .to_string();
+        let ttl_ms = record["ttl_ms"].as_i64().filter(|ttl| *ttl > 0);
+        
+        let result: RedisResult<()> = if let Some(payload) = record["dump"].as_str() {
+            let payload = BASE64_STANDARD.decode(payload)
+                .map_err(|e| anyhow::anyhow!("Invalid dump payload for '{}': {}", key, e))?;
+            let mut cmd = redis::cmd("RESTORE");
+            cmd.arg(&key).arg(ttl_ms.unwrap_or(0)).arg(payload);
+            if replace {
+                cmd.arg("REPLACE");
+            }
+            cmd.query_async(manager).await
+        } else {
+            let exists: bool = manager.exists(&key).await?;
+            if exists && !replace {
+                conflicts.push(key);
+                continue;
+            }
+            
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+            if exists {
+                pipe.cmd("DEL").arg(&key).ignore();
+            }
+            let key_type = record["type"].as_str().unwrap_or("string");
+            if let Err(e) = add_import_commands(&mut pipe, &key, key_type, &record["value"]) {
+                failed.push(json!({ "key": key, "error": e.to_string() }));
+                continue;
+            }
+            if let Some(ttl_ms) = ttl_ms {
+                pipe.cmd("PEXPIRE").arg(&key).arg(ttl_ms).ignore();
+            }
+            pipe.query_async(manager).await
+        };
+        
+        match result {
+            Ok(()) => imported += 1,
+            Err(e) if e.code() == Some("BUSYKEY") => conflicts.push(key),
+            Err(e) => failed.push(json!({ "key": key, "error": e.to_string() })),
+        }
+    }
+    
+    let response = json!({
+        "file": file,
+        "imported": imported,
+        "conflicts": conflicts.len(),
+        "conflicting_keys": conflicts,
+        "failed": failed.len(),
+        "failures": failed
+    });
+    println!("{}", format_output(&response, options.format));
+    
+    if !failed.is_empty() {
+        return Err(anyhow::anyhow!("{} key(s) failed to import", failed.len()));
+    }
+    
+    Ok(())
+}
+
+// Memory analysis
+#[derive(Default)]
+struct KeyGroupStats {
+    keys: u64,
+    memory_bytes: i64,
+    without_ttl: u64,
+    encodings: std::collections::BTreeMap<String, u64>,
+}
+
+impl KeyGroupStats {
+    fn add(&mut self, memory_bytes: i64, encoding: &str, ttl: i64) {
+        self.keys += 1;
+        self.memory_bytes += memory_bytes;
+        if ttl == -1 {
+            self.without_ttl += 1;
+        }
+        *self.encodings.entry(encoding.to_string()).or_default() += 1;
+    }
+    
+    fn to_json(&self, name: (&str, &str), scale: Option<f64>) -> serde_json::Value {
+        json!({
+            name.0: name.1,
+            "keys": self.keys,
+            "estimated_keys": scale.map(|scale| (self.keys as f64 * scale).round() as u64),
+            "memory_bytes": self.memory_bytes,
+            "estimated_memory_bytes": scale.map(|scale| (self.memory_bytes as f64 * scale).round() as i64),
+            "without_ttl": self.without_ttl,
+            "encodings": self.encodings
+        })
+    }
+}
+
+/// How many keys each sampled key stands for. A complete scan offered every
+/// matching key for sampling. A scan cut short by --max-keys covered an
+/// unknown share of them, known only from the total number of keys when
+/// every key matches the pattern
+fn estimate_scale(sample_rate: f64, scan_complete: bool, sampled: usize, total_keys: Option<u64>) -> Option<f64> {
+    if scan_complete {
+        return Some(1.0 / sample_rate);
+    }
+    total_keys.filter(|_| sampled > 0).map(|total| total as f64 / sampled as f64)
+}
+
+/// The first `depth` delimiter-separated segments of a key, excluding the
+/// final segment so a key is never its own prefix
+fn key_prefix(key: &str, delimiter: &str, depth: usize) -> Option<String> {
+    if delimiter.is_empty() {
+        return None;
+    }
+    let segments: Vec<&str> = key.split(delimiter).collect();
+    let take = depth.min(segments.len() - 1);
+    (take > 0).then(|| segments[..take].join(delimiter))
+}
+
+/// Keep the `n` largest entries of a min-heap
+fn push_top<T: Ord>(heap: &mut std::collections::BinaryHeap<std::cmp::Reverse<T>>, n: usize, item: T) {
+    heap.push(std::cmp::Reverse(item));
+    if heap.len() > n {
+        heap.pop();
+    }
+}
+
+async fn handle_analyze_command(
+    manager: &mut ConnectionManager,
+    pattern: String,
+    analyze: AnalyzeOptions,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    use std::collections::{BinaryHeap, HashMap};
+    
+    if !(analyze.sample_rate > 0.0 && analyze.sample_rate <= 1.0) {
+        return Err(anyhow::anyhow!("--sample-rate must be in (0, 1], got {}", analyze.sample_rate));
+    }
+    
+    let mut by_type: HashMap<String, KeyGroupStats> = HashMap::new();
+    let mut by_prefix: HashMap<String, KeyGroupStats> = HashMap::new();
+    let mut biggest = BinaryHeap::new();
+    let mut biggest_without_ttl = BinaryHeap::new();
+    let mut scanned = 0u64;
+    let mut sampled = 0usize;
+    let mut cursor: u64 = 0;
+    
+    loop {
+        let (next, batch): (u64, Vec<String>) = redis::cmd("SCAN")
+            .arg(cursor).arg("MATCH").arg(&pattern).arg("COUNT").arg(analyze.count.max(1))
+            .query_async(manager)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to scan keys with pattern '{}': {}", pattern, e))?;
+        cursor = next;
+        scanned += batch.len() as u64;
+        
+        let mut keys: Vec<String> = batch.into_iter()
+            .filter(|_| analyze.sample_rate >= 1.0 || rand::random::<f64>() < analyze.sample_rate)
+            .collect();
+        if let Some(max_keys) = analyze.max_keys {
+            keys.truncate(max_keys.saturating_sub(sampled));
+        }
+        
+        if !keys.is_empty() {
+            let mut pipe = redis::pipe();
+            for key in &keys {
+                pipe.cmd("TYPE").arg(key)
+                    .cmd("MEMORY").arg("USAGE").arg(key)
+                    .cmd("OBJECT").arg("ENCODING").arg(key)
+                    .cmd("TTL").arg(key);
+            }
+            let details: Vec<(String, Option<i64>, Option<String>, i64)> = pipe.query_async(manager).await
+                .map_err(|e| anyhow::anyhow!("Failed to inspect keys: {}", e))?;
+            
+            for (key, (key_type, memory, encoding, ttl)) in keys.into_iter().zip(details) {
+                // The key expired or was deleted since it was scanned
+                let Some(memory) = memory.filter(|_| key_type != "none") else {
+                    continue;
+                };
+                sampled += 1;
+                let encoding = encoding.unwrap_or_default();
+                
+                by_type.entry(key_type.clone()).or_default().add(memory, &encoding, ttl);
+                let prefix = key_prefix(&key, &analyze.delimiter, analyze.depth)
+                    .unwrap_or_else(|| "(no prefix)".to_string());
+                by_prefix.entry(prefix).or_default().add(memory, &encoding, ttl);
+                
+                if ttl == -1 {
+                    push_top(&mut biggest_without_ttl, analyze.top, (memory, key.clone()));
+                }
+                push_top(&mut biggest, analyze.top, (memory, key, key_type, encoding, ttl));
+            }
+        }
+        
+        if cursor == 0 || analyze.max_keys.is_some_and(|max_keys| sampled >= max_keys) {
+            break;
+        }
+    }
+    
+    let scan_complete = cursor == 0;
+    let total_keys = if !scan_complete && pattern == "*" {
+        let total: u64 = redis::cmd("DBSIZE").query_async(manager).await
+            .map_err(|e| anyhow::anyhow!("Failed to get database size: {}", e))?;
+        Some(total)
+    } else {
+        None
+    };
+    let scale = estimate_scale(analyze.sample_rate, scan_complete, sampled, total_keys);
+    
+    let mut types: Vec<_> = by_type.iter().collect();
+    types.sort_by(|a, b| b.1.memory_bytes.cmp(&a.1.memory_bytes).then(a.0.cmp(b.0)));
+    let mut prefixes: Vec<_> = by_prefix.iter().collect();
+    prefixes.sort_by(|a, b| b.1.memory_bytes.cmp(&a.1.memory_bytes).then(a.0.cmp(b.0)));
+    
+    let biggest: Vec<serde_json::Value> = biggest.into_sorted_vec().into_iter()
+        .map(|std::cmp::Reverse((memory, key, key_type, encoding, ttl))| json!({
+            "key": key,
+            "type": key_type,
+            "encoding": encoding,
+            "memory_bytes": memory,
+            "ttl_seconds": ttl
+        }))
+        .collect();
+    let without_ttl: Vec<serde_json::Value> = biggest_without_ttl.into_sorted_vec().into_iter()
+        .map(|std::cmp::Reverse((memory, key))| json!({ "key": key, "memory_bytes": memory }))
+        .collect();
+    let total_memory: i64 = by_type.values().map(|stats| stats.memory_bytes).sum();
+    let total_without_ttl: u64 = by_type.values().map(|stats| stats.without_ttl).sum();
+    
+    let response = json!({
+        "pattern": pattern,
+        "sample_rate": analyze.sample_rate,
+        "keys_scanned": scanned,
+        "keys_sampled": sampled,
+        "scan_complete": scan_complete,
+        "memory_bytes": total_memory,
+        "estimated_memory_bytes": scale.map(|scale| (total_memory as f64 * scale).round() as i64),
+        "by_type": types.into_iter().map(|(name, stats)| stats.to_json(("type", name), scale)).collect::<Vec<_>>(),
+        "by_prefix": prefixes.into_iter().map(|(name, stats)| stats.to_json(("prefix", name), scale)).collect::<Vec<_>>(),
+        "biggest_keys": biggest,
+        "keys_without_ttl": {
+            "count": total_without_ttl,
+            "estimated_count": scale.map(|scale| (total_without_ttl as f64 * scale).round() as u64),
+            "biggest": without_ttl
+        }
+    });
+    println!("{}", format_output(&response, options.format));
+    
+    Ok(())
+}
+
+// Locks and rate limiting
+/// Delete the lock only if it still holds our token: 1 released, 0 held by another owner, -1 not held
+const LOCK_RELEASE_SCRIPT: &str = r#"
+local current = redis.call('GET', KEYS[1])
+if not current then return -1 end
+if current ~= ARGV[1] then return 0 end
+return redis.call('DEL', KEYS[1])
+"#;
+
+/// Reset the lock's TTL only if it still holds our token, with the same replies as release
+const LOCK_EXTEND_SCRIPT: &str = r#"
+local current = redis.call('GET', KEYS[1])
+if not current then return -1 end
+if current ~= ARGV[1] then return 0 end
+return redis.call('PEXPIRE', KEYS[1], ARGV[2])
+"#;
+
+/// ARGV: window_ms, limit, cost, unique id. Returns {allowed, remaining, retry_after_ms}
+const SLIDING_WINDOW_SCRIPT: &str = r#"
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local window = tonumber(ARGV[1])
+local limit = tonumber(ARGV[2])
+local cost = tonumber(ARGV[3])
+redis.call('ZREMRANGEBYSCORE', KEYS[1], '-inf', now - window)
+local count = redis.call('ZCARD', KEYS[1])
+if count + cost <= limit then
+    for i = 1, cost do
+        redis.call('ZADD', KEYS[1], now, now .. '-' .. ARGV[4] .. '-' .. i)
+    end
+    redis.call('PEXPIRE', KEYS[1], window)
+    return {1, limit - count - cost, 0}
+end
+local oldest = redis.call('ZRANGE', KEYS[1], count + cost - limit - 1, count + cost - limit - 1, 'WITHSCORES')
+local retry = 0
+if oldest[2] then retry = tonumber(oldest[2]) + window - now end
+return {0, limit - count, retry}
+"#;
+
+/// ARGV: window_ms, capacity, cost. Returns {allowed, remaining, retry_after_ms}
+const TOKEN_BUCKET_SCRIPT: &str = r#"
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local window = tonumber(ARGV[1])
+local capacity = tonumber(ARGV[2])
+local cost = tonumber(ARGV[3])
+local rate = capacity / window
+local state = redis.call('HMGET', KEYS[1], 'tokens', 'ts')
+local tokens = tonumber(state[1]) or capacity
+local ts = tonumber(state[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - ts) * rate)
+local allowed = 0
+local retry = 0
+if tokens >= cost then
+    tokens = tokens - cost
+    allowed = 1
+else
+    retry = math.ceil((cost - tokens) / rate)
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'ts', now)
+redis.call('PEXPIRE', KEYS[1], math.ceil(window))
+return {allowed, math.floor(tokens), retry}
+"#;
+
+fn seconds_to_millis(seconds: f64, name: &str) -> Result<u64, anyhow::Error> {
+    let millis = (seconds * 1000.0).round();
+    // Also rejects NaN and infinity, which would saturate the cast
+    if !(1.0..=u64::MAX as f64).contains(&millis) {
+        return Err(anyhow::anyhow!("{} must be a finite duration of at least 1 millisecond, got {} seconds", name, seconds));
+    }
+    Ok(millis as u64)
+}
+
+/// A request can't cost nothing or more than the whole limit, which could never be allowed
+fn validate_rate_limit(limit: u64, cost: u64) -> Result<(), anyhow::Error> {
+    if limit == 0 {
+        return Err(anyhow::anyhow!("--limit must be at least 1"));
+    }
+    if cost == 0 || cost > limit {
+        return Err(anyhow::anyhow!("--cost must be between 1 and the limit ({}), got {}", limit, cost));
+    }
+    Ok(())
+}
+
+async fn handle_lock_acquire(
+    manager: &mut ConnectionManager,
+    key: String,
+    ttl: f64,
+    token: Option<String>,
+    wait: Option<f64>,
+    retry_interval: u64,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let ttl_ms = seconds_to_millis(ttl, "--ttl")?;
+    let token = token.unwrap_or_else(|| hex::encode(rand::random::<[u8; 16]>()));
+    let deadline = wait.map(|seconds| std::time::Instant::now() + std::time::Duration::from_secs_f64(seconds.max(0.0)));
+    
+    let mut attempts = 0;
+    let acquired = loop {
+        attempts += 1;
+        let reply: Option<String> = redis::cmd("SET").arg(&key).arg(&token).arg("NX").arg("PX").arg(ttl_ms)
+            .query_async(manager)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to acquire lock '{}': {}", key, e))?;
+        if reply.is_some() {
+            break true;
+        }
+        if deadline.is_none_or(|deadline| std::time::Instant::now() >= deadline) {
+            break false;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(retry_interval)).await;
+    };
+    
+    let holder_ttl_ms: Option<i64> = if acquired {
+        None
+    } else {
+        Some(manager.pttl(&key).await?)
+    };
+    let response = json!({
+        "key": key,
+        "acquired": acquired,
+        "token": if acquired { Some(&token) } else { None },
+        "ttl_ms": ttl_ms,
+        "attempts": attempts,
+        "holder_ttl_ms": holder_ttl_ms
+    });
+    println!("{}", format_output(&response, options.format));
+    
+    if !acquired {
+        return Err(anyhow::anyhow!("Lock '{}' is held by another owner", key));
+    }
+    
+    Ok(())
+}
+
+async fn handle_lock_token_script(
+    manager: &mut ConnectionManager,
+    key: String,
+    token: String,
+    ttl: Option<f64>,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let (script, action) = match ttl {
+        Some(_) => (LOCK_EXTEND_SCRIPT, "extended"),
+        None => (LOCK_RELEASE_SCRIPT, "released"),
+    };
+    let ttl_ms = ttl.map(|ttl| seconds_to_millis(ttl, "--ttl")).transpose()?;
+    
+    let script = redis::Script::new(script);
+    let mut invocation = script.prepare_invoke();
+    invocation.key(&key).arg(&token);
+    if let Some(ttl_ms) = ttl_ms {
+        invocation.arg(ttl_ms);
+    }
+    let status: i64 = invocation.invoke_async(manager).await
+        .map_err(|e| anyhow::anyhow!("Failed to update lock '{}': {}", key, e))?;
+    
+    let response = json!({
+        "key": key,
+        action: status == 1,
+        "ttl_ms": ttl_ms,
+        "reason": match status {
+            1 => None,
+            0 => Some("held_by_other_owner"),
+            _ => Some("not_held"),
+        }
+    });
+    println!("{}", format_output(&response, options.format));
+    
+    if status != 1 {
+        return Err(anyhow::anyhow!("Lock '{}' was not {}: it is no longer held with this token", key, action));
+    }
+    
+    Ok(())
+}
+
+async fn handle_rate_limit_command(
+    manager: &mut ConnectionManager,
+    key: String,
+    limit: u64,
+    window: f64,
+    algorithm: RateLimitAlgorithm,
+    cost: u64,
+    options: &CommonOptions,
+) -> Result<(), anyhow::Error> {
+    let window_ms = seconds_to_millis(window, "--window")?;
+    validate_rate_limit(limit, cost)?;
+    
+    let script = redis::Script::new(match algorithm {
+        RateLimitAlgorithm::SlidingWindow => SLIDING_WINDOW_SCRIPT,
+        RateLimitAlgorithm::TokenBucket => TOKEN_BUCKET_SCRIPT,
+    });
+    let mut invocation = script.prepare_invoke();
+    invocation.key(&key).arg(window_ms).arg(limit).arg(cost);
+    if algorithm == RateLimitAlgorithm::SlidingWindow {
+        // Makes the sorted set members of concurrent requests unique
+        invocation.arg(hex::encode(rand::random::<[u8; 8]>()));
+    }
+    let (allowed, remaining, retry_after_ms): (i64, i64, i64) = invocation.invoke_async(manager).await
+        .map_err(|e| anyhow::anyhow!("Failed to check rate limit '{}': {}", key, e))?;
+    
+    let response = json!({
+        "key": key,
+        "algorithm": match algorithm {
+            RateLimitAlgorithm::SlidingWindow => "sliding-window",
+            RateLimitAlgorithm::TokenBucket => "token-bucket",
+        },
+        "allowed": allowed == 1,
+        "limit": limit,
+        "remaining": remaining,
+        "window_ms": window_ms,
+        "retry_after_ms": retry_after_ms
+    });
+    println!("{}", format_output(&response, options.format));
+    
+    if allowed != 1 {
+        return Err(anyhow::anyhow!("Rate limit '{}' exceeded, retry after {} ms", key, retry_after_ms));
+    }
+    
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    let options = CommonOptions::new(cli.format, cli.debug);
+    options.setup_debug();
+    
+    let mut manager = match create_connection_manager(&cli.url).await {
+        Ok(manager) => manager,
+        Err(e) => handle_error(e, "Failed to create Redis connection manager"),
+    };
+    
+    let result = match cli.command {
+        Commands::Set { key, value, expire, value_options } => {
+            handle_set_command(&mut manager, key, value, expire, value_options, &options).await
+        }
+        Commands::Get { key, key_type, value_options } => {
+            handle_get_command(&mut manager, key, key_type, value_options, &options).await
         }
         Commands::Delete { keys } => {
             handle_delete_command(&mut manager, keys, &options).await
         }
-        Commands::List { pattern, limit } => {
-            handle_list_command(&mut manager, pattern, limit, &options).await
+        Commands::List { pattern, list } => {
+            handle_list_command(&mut manager, pattern, list, &options).await
         }
         Commands::Expire { key, seconds } => {
             handle_expire_command(&mut manager, key, seconds, &options).await
@@ -857,6 +3307,157 @@ async fn main() {
                 }
             }
         }
+        Commands::Zset { operation } => {
+            match operation {
+                SortedSetOperation::Add { key, members, add } => {
+                    handle_zset_add(&mut manager, key, members, add, &options).await
+                }
+                SortedSetOperation::Range { key, start, stop, range } => {
+                    handle_zset_range(&mut manager, key, start, stop, range, &options).await
+                }
+                SortedSetOperation::Remove { key, members } => {
+                    let result: RedisResult<i32> = manager.zrem(&key, &members).await;
+                    match result {
+                        Ok(removed) => {
+                            let response = json!({
+                                "key": key,
+                                "members_removed": removed,
+                                "members_requested": members.len()
+                            });
+                            println!("{}", format_output(&response, options.format));
+                            Ok(())
+                        }
+                        Err(e) => Err(anyhow::anyhow!("Failed to remove members from sorted set '{}': {}", key, e)),
+                    }
+                }
+                SortedSetOperation::IncrBy { key, increment, member } => {
+                    let result: RedisResult<f64> = manager.zincr(&key, &member, increment).await;
+                    match result {
+                        Ok(score) => {
+                            let response = json!({
+                                "key": key,
+                                "member": member,
+                                "increment": increment,
+                                "score": score_to_json(score)
+                            });
+                            println!("{}", format_output(&response, options.format));
+                            Ok(())
+                        }
+                        Err(e) => Err(anyhow::anyhow!("Failed to increment member '{}' in sorted set '{}': {}", member, key, e)),
+                    }
+                }
+                SortedSetOperation::Rank { key, member, rev } => {
+                    let result: RedisResult<(Option<i64>, Option<f64>)> = redis::pipe()
+                        .cmd(if rev { "ZREVRANK" } else { "ZRANK" }).arg(&key).arg(&member)
+                        .cmd("ZSCORE").arg(&key).arg(&member)
+                        .query_async(&mut manager)
+                        .await;
+                    match result {
+                        Ok((rank, score)) => {
+                            let response = json!({
+                                "key": key,
+                                "member": member,
+                                "rank": rank,
+                                "score": score.map(score_to_json),
+                                "exists": rank.is_some()
+                            });
+                            println!("{}", format_output(&response, options.format));
+                            Ok(())
+                        }
+                        Err(e) => Err(anyhow::anyhow!("Failed to get rank of member '{}' in sorted set '{}': {}", member, key, e)),
+                    }
+                }
+                SortedSetOperation::Card { key } => {
+                    let result: RedisResult<i64> = manager.zcard(&key).await;
+                    match result {
+                        Ok(cardinality) => {
+                            let response = json!({
+                                "key": key,
+                                "cardinality": cardinality
+                            });
+                            println!("{}", format_output(&response, options.format));
+                            Ok(())
+                        }
+                        Err(e) => Err(anyhow::anyhow!("Failed to get cardinality of sorted set '{}': {}", key, e)),
+                    }
+                }
+                SortedSetOperation::PopMin { key, count } => {
+                    handle_zset_pop(&mut manager, key, count, false, &options).await
+                }
+                SortedSetOperation::PopMax { key, count } => {
+                    handle_zset_pop(&mut manager, key, count, true, &options).await
+                }
+            }
+        }
+        Commands::Stream { operation } => {
+            match operation {
+                StreamOperation::Add { key, fields, id, maxlen, approximate } => {
+                    handle_stream_add(&mut manager, key, fields, id, maxlen, approximate, &options).await
+                }
+                StreamOperation::Range { key, start, end, count, reverse } => {
+                    handle_stream_range(&mut manager, key, start, end, count, reverse, &options).await
+                }
+                StreamOperation::Read { keys, id, read, follow } => {
+                    if follow {
+                        handle_stream_follow(&mut manager, keys, id, read).await
+                    } else {
+                        handle_stream_read(&mut manager, keys, id, read, &options).await
+                    }
+                }
+                StreamOperation::GroupCreate { key, group, id, mkstream } => {
+                    handle_stream_group_create(&mut manager, key, group, id, mkstream, &options).await
+                }
+                StreamOperation::ReadGroup { member, keys, id, read, noack } => {
+                    handle_stream_read_group(&mut manager, member, keys, id, read, noack, &options).await
+                }
+                StreamOperation::Ack { key, group, ids } => {
+                    handle_stream_ack(&mut manager, key, group, ids, &options).await
+                }
+                StreamOperation::Pending { key, group, count, consumer, idle } => {
+                    handle_stream_pending(&mut manager, key, group, count, consumer, idle, &options).await
+                }
+                StreamOperation::Trim { key, maxlen, minid, approximate } => {
+                    handle_stream_trim(&mut manager, key, maxlen, minid, approximate, &options).await
+                }
+            }
+        }
+        Commands::Publish { channel, message } => {
+            handle_publish_command(&mut manager, channel, message, &options).await
+        }
+        Commands::Subscribe { channels, count, timeout } => {
+            handle_subscribe_command(&cli.url, channels, count, timeout).await
+        }
+        Commands::Eval { script, keys, args } => {
+            handle_eval_command(&mut manager, script, keys, args, &options).await
+        }
+        Commands::Tx { file, watch } => {
+            handle_tx_command(&cli.url, file, watch, &options).await
+        }
+        Commands::Export { pattern, output, dump, count, key_type } => {
+            handle_export_command(&mut manager, pattern, output, dump, count, key_type, &options).await
+        }
+        Commands::Import { file, replace } => {
+            handle_import_command(&mut manager, file, replace, &options).await
+        }
+        Commands::Analyze { pattern, analyze } => {
+            handle_analyze_command(&mut manager, pattern, analyze, &options).await
+        }
+        Commands::Lock { operation } => {
+            match operation {
+                LockOperation::Acquire { key, ttl, token, wait, retry_interval } => {
+                    handle_lock_acquire(&mut manager, key, ttl, token, wait, retry_interval, &options).await
+                }
+                LockOperation::Release { key, token } => {
+                    handle_lock_token_script(&mut manager, key, token, None, &options).await
+                }
+                LockOperation::Extend { key, token, ttl } => {
+                    handle_lock_token_script(&mut manager, key, token, Some(ttl), &options).await
+                }
+            }
+        }
+        Commands::RateLimit { key, limit, window, algorithm, cost } => {
+            handle_rate_limit_command(&mut manager, key, limit, window, algorithm, cost, &options).await
+        }
         Commands::Info { section } => {
             handle_info_command(&mut manager, section, &options).await
         }
@@ -874,4 +3475,188 @@ async fn main() {
     if let Err(e) = result {
         handle_error(e, "Redis command execution failed");
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn data(item: &str) -> redis::Value {
+        redis::Value::Data(item.as_bytes().to_vec())
+    }
+    
+    fn bulk(items: &[&str]) -> redis::Value {
+        redis::Value::Bulk(items.iter().map(|item| data(item)).collect())
+    }
+    
+    /// The commands queued on a pipeline, one argument list each
+    fn queued_commands(pipe: &redis::Pipeline) -> Vec<Vec<String>> {
+        pipe.cmd_iter()
+            .map(|cmd| cmd.args_iter().map(|arg| match arg {
+                redis::Arg::Simple(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+                redis::Arg::Cursor => "0".to_string(),
+            }).collect())
+            .collect()
+    }
+    
+    fn import_commands(key_type: &str, value: &serde_json::Value) -> Result<Vec<Vec<String>>, anyhow::Error> {
+        let mut pipe = redis::pipe();
+        add_import_commands(&mut pipe, "k", key_type, value)?;
+        Ok(queued_commands(&pipe))
+    }
+    
+    #[test]
+    fn test_export_import_round_trip() {
+        let stream = redis::Value::Bulk(vec![
+            redis::Value::Bulk(vec![data("1-0"), bulk(&["a", "1"])]),
+            redis::Value::Bulk(vec![data("2-0"), bulk(&["b", "2"])]),
+        ]);
+        let cases = [
+            ("string", data("text"), vec![vec!["SET", "k", "text"]]),
+            ("list", bulk(&["x", "y", "x"]), vec![vec!["RPUSH", "k", "x", "y", "x"]]),
+            ("set", bulk(&["y", "x"]), vec![vec!["SADD", "k", "x", "y"]]),
+            ("hash", bulk(&["a", "1", "b", "{\"n\":2}"]), vec![vec!["HSET", "k", "a", "1", "b", "{\"n\":2}"]]),
+            (
+                "zset",
+                bulk(&["low", "-inf", "mid", "1.5", "high", "inf"]),
+                vec![vec!["ZADD", "k", "-inf", "low", "1.5", "mid", "inf", "high"]],
+            ),
+            ("stream", stream, vec![vec!["XADD", "k", "1-0", "a", "1"], vec!["XADD", "k", "2-0", "b", "2"]]),
+        ];
+        
+        for (key_type, reply, expected) in cases {
+            let exported = export_value_to_json(key_type, &reply);
+            // Records are written as JSON text, so values must survive serialization
+            let exported: serde_json::Value = serde_json::from_str(&exported.to_string()).unwrap();
+            assert_eq!(import_commands(key_type, &exported).unwrap(), expected, "type {}", key_type);
+        }
+    }
+    
+    #[test]
+    fn test_export_falls_back_to_dump() {
+        let record = export_record("k", "string", Some(5000), &data("text")).unwrap();
+        assert_eq!(record, json!({"key": "k", "type": "string", "ttl_ms": 5000, "value": "text"}));
+        
+        // Values that aren't valid UTF-8 anywhere in the reply need a DUMP payload
+        let binary = redis::Value::Data(vec![0xff, 0x00]);
+        assert!(export_record("k", "string", None, &binary).is_none());
+        assert!(export_record("k", "list", None, &redis::Value::Bulk(vec![data("a"), binary])).is_none());
+        
+        let record = dump_record("k", "string", None, &[0xff, 0x00]);
+        assert_eq!(record, json!({"key": "k", "type": "string", "ttl_ms": null, "dump": "/wA="}));
+    }
+    
+    #[test]
+    fn test_export_infinite_scores() {
+        let exported = export_value_to_json("zset", &bulk(&["a", "-inf", "b", "inf"]));
+        assert_eq!(exported, json!([{"member": "a", "score": "-inf"}, {"member": "b", "score": "inf"}]));
+    }
+    
+    #[test]
+    fn test_import_rejects_unusable_values() {
+        for (key_type, value) in [
+            ("stream", json!([])),
+            ("list", json!([])),
+            ("hash", json!({})),
+            ("zset", json!([{"member": "a", "score": null}])),
+            ("zset", json!([{"member": "a", "score": "nan"}])),
+            ("module", json!("x")),
+        ] {
+            assert!(import_commands(key_type, &value).is_err(), "{} {}", key_type, value);
+        }
+    }
+    
+    #[test]
+    fn test_json_command_args() {
+        assert_eq!(
+            json_command_args(&json!(["SET", "k", 1, 2.5, true, {"a": 1}])).unwrap(),
+            vec!["SET", "k", "1", "2.5", "true", "{\"a\":1}"]
+        );
+        assert!(json_command_args(&json!([])).is_err());
+        assert!(json_command_args(&json!("SET k v")).is_err());
+    }
+    
+    #[test]
+    fn test_parse_tx_document() {
+        let (watch, commands) = parse_tx_document(r#"[["SET", "k", "v"], ["INCR", "n"]]"#).unwrap();
+        assert!(watch.is_empty());
+        assert_eq!(commands, vec![vec!["SET", "k", "v"], vec!["INCR", "n"]]);
+        
+        let (watch, commands) = parse_tx_document(r#"{"watch": ["a", 1], "commands": [["GET", "a"]]}"#).unwrap();
+        assert_eq!(watch, vec!["a", "1"]);
+        assert_eq!(commands, vec![vec!["GET", "a"]]);
+        
+        for bad in ["[]", r#"{"watch": ["a"]}"#, r#"{"commands": "GET a"}"#, r#"[["GET"], []]"#, "not json"] {
+            assert!(parse_tx_document(bad).is_err(), "{}", bad);
+        }
+    }
+    
+    #[test]
+    fn test_parse_raw_reply() {
+        let exec = b"*4\r\n+OK\r\n-ERR value is not an integer\r\n$3\r\nv\r\n\r\n*2\r\n:1\r\n$-1\r\n";
+        let expected = RawReply::Array(vec![
+            RawReply::Status("OK".to_string()),
+            RawReply::Error("ERR value is not an integer".to_string()),
+            RawReply::Data(b"v\r\n".to_vec()),
+            RawReply::Array(vec![RawReply::Int(1), RawReply::Nil]),
+        ]);
+        assert_eq!(parse_raw_reply(exec).unwrap(), Some((expected, exec.len())));
+        
+        // Incomplete replies wait for more data
+        for end in 0..exec.len() {
+            assert_eq!(parse_raw_reply(&exec[..end]).unwrap(), None, "prefix of {} bytes", end);
+        }
+        
+        let two = b"+QUEUED\r\n*-1\r\n";
+        assert_eq!(parse_raw_reply(two).unwrap(), Some((RawReply::Status("QUEUED".to_string()), 9)));
+        assert_eq!(parse_raw_reply(&two[9..]).unwrap(), Some((RawReply::Nil, 5)));
+        
+        assert!(parse_raw_reply(b"?\r\n").is_err());
+        assert!(parse_raw_reply(b"$x\r\n").is_err());
+    }
+    
+    #[test]
+    fn test_seconds_to_millis() {
+        assert_eq!(seconds_to_millis(30.0, "--ttl").unwrap(), 30_000);
+        assert_eq!(seconds_to_millis(0.25, "--ttl").unwrap(), 250);
+        // Rounded to the nearest millisecond
+        assert_eq!(seconds_to_millis(0.0015, "--ttl").unwrap(), 2);
+        assert_eq!(seconds_to_millis(0.0005, "--ttl").unwrap(), 1);
+        
+        for bad in [0.0, 0.0004, -1.0, f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(seconds_to_millis(bad, "--ttl").is_err(), "{}", bad);
+        }
+        assert!(seconds_to_millis(0.0, "--window").unwrap_err().to_string().starts_with("--window"));
+    }
+    
+    #[test]
+    fn test_validate_rate_limit() {
+        validate_rate_limit(10, 1).unwrap();
+        validate_rate_limit(10, 10).unwrap();
+        assert!(validate_rate_limit(10, 0).is_err());
+        assert!(validate_rate_limit(10, 11).is_err());
+        assert!(validate_rate_limit(0, 0).is_err());
+    }
+    
+    #[test]
+    fn test_key_prefix() {
+        assert_eq!(key_prefix("user:42:profile", ":", 1).as_deref(), Some("user"));
+        assert_eq!(key_prefix("user:42:profile", ":", 2).as_deref(), Some("user:42"));
+        // The last segment is never part of the prefix
+        assert_eq!(key_prefix("user:42:profile", ":", 5).as_deref(), Some("user:42"));
+        assert_eq!(key_prefix("user::x", ":", 2).as_deref(), Some("user:"));
+        assert_eq!(key_prefix("a--b--c", "--", 1).as_deref(), Some("a"));
+        assert_eq!(key_prefix("plain", ":", 1), None);
+        assert_eq!(key_prefix("user:42", ":", 0), None);
+        assert_eq!(key_prefix("user:42", "", 1), None);
+    }
+    
+    #[test]
+    fn test_estimate_scale() {
+        assert_eq!(estimate_scale(1.0, true, 10, None), Some(1.0));
+        assert_eq!(estimate_scale(0.25, true, 10, Some(1000)), Some(4.0));
+        // An incomplete scan is scaled to the whole keyspace when its size is known
+        assert_eq!(estimate_scale(0.5, false, 100, Some(1000)), Some(10.0));
+        assert_eq!(estimate_scale(1.0, false, 100, None), None);
+        assert_eq!(estimate_scale(1.0, false, 0, Some(1000)), None);
+    }
+}
@@ -3,6 +3,6 @@ pub mod error;
 pub mod cli;
 
 // Re-export commonly used items
-pub use output::{OutputFormat, format_output};
+pub use output::{OutputFormat, format_output, format_csv_row};
 pub use error::handle_error;
 pub use cli::{CommonOptions, get_env_or_default, parse_json_arg};
\ No newline at end of file
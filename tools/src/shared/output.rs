@@ -116,6 +116,14 @@ fn format_as_csv(data: &Value) -> String {
     }
 }
 
+/// Format a single CSV line from values in column order
+pub fn format_csv_row(values: &[Value]) -> String {
+    values.iter()
+        .map(|value| escape_csv_value(&format_csv_value(value)))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
 /// Format a JSON value for CSV output
 fn format_csv_value(value: &Value) -> String {
     match value {
@@ -182,4 +190,10 @@ mod tests {
         assert_eq!(escape_csv_value("with,comma"), "\"with,comma\"");
         assert_eq!(escape_csv_value("with\"quote"), "\"with\"\"quote\"");
     }
+
+    #[test]
+    fn test_csv_row() {
+        let row = format_csv_row(&[json!(1), json!("a,b"), Value::Null, json!({"k": true})]);
+        assert_eq!(row, "1,\"a,b\",,\"{\"\"k\"\":true}\"");
+    }
 }
\ No newline at end of file